@@ -0,0 +1,94 @@
+use crate::json::{self, JsonValue};
+use crate::parse::ParsedTestGroup;
+use std::path::{Path, PathBuf};
+
+/// Gets the path a baseline is saved to, baselines are stored in `target/ptest/bench/<name>.json`.
+pub fn baseline_path(target_dir: &Path, name: &str) -> PathBuf {
+    target_dir
+        .join("ptest")
+        .join("bench")
+        .join(format!("{}.json", name))
+}
+
+/// Identifies the group a benchmark is from so that it can be found in a baseline, e.g. `lib benchp`.
+fn group_key(group: &ParsedTestGroup) -> String {
+    match &group.target {
+        Some(res) => res.to_string(),
+        None => group.crate_name.clone(),
+    }
+}
+
+/// Saves the results of the benchmarks to path so that later runs can be compared against them with --baseline.
+/// ``` text
+/// {"benchmarks":[{"group":"lib benchp","module_path":"benches::sum","ns_per_iter":0.84,"deviation":0.3}]}
+/// ```
+pub fn save_baseline(groups: &[ParsedTestGroup], path: &Path) -> Result<(), String> {
+    let benchmarks: Vec<String> = groups
+        .iter()
+        .flat_map(|group| {
+            group.tests.iter().filter_map(move |test| {
+                let bench = test.bench.as_ref()?;
+
+                Some(format!(
+                    "{{\"group\":{},\"module_path\":{},\"ns_per_iter\":{},\"deviation\":{}}}",
+                    json::string(group_key(group).as_str()),
+                    json::string(&test.module_path),
+                    bench.ns_per_iter,
+                    bench.deviation
+                ))
+            })
+        })
+        .collect();
+
+    if let Some(parent) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        return Err(format!("Could not create {}: {}", parent.display(), e));
+    }
+
+    match std::fs::write(path, format!("{{\"benchmarks\":[{}]}}\n", benchmarks.join(","))) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Could not save baseline to {}: {}", path.display(), e)),
+    }
+}
+
+/// Loads the baseline saved at path and sets [crate::parse::BenchResult::baseline] for every benchmark that is in it.
+pub fn compare_baseline(groups: &mut [ParsedTestGroup], path: &Path) -> Result<(), String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) => return Err(format!("Could not read baseline {}: {}", path.display(), e)),
+    };
+
+    let baseline = match JsonValue::parse(contents.trim()) {
+        Some(res) => res,
+        None => return Err(format!("Baseline {} is not valid JSON", path.display())),
+    };
+
+    let saved = match baseline.get("benchmarks").and_then(|x| x.as_array()) {
+        Some(res) => res,
+        None => return Err(format!("Baseline {} has no benchmarks", path.display())),
+    };
+
+    for group in groups.iter_mut() {
+        let key = group_key(group);
+
+        for test in group.tests.iter_mut() {
+            let bench = match test.bench.as_mut() {
+                Some(res) => res,
+                None => continue,
+            };
+
+            bench.baseline = saved
+                .iter()
+                .find(|x| {
+                    x.get("group").and_then(|y| y.as_str()) == Some(key.as_str())
+                        && x.get("module_path").and_then(|y| y.as_str())
+                            == Some(test.module_path.as_str())
+                })
+                .and_then(|x| x.get("ns_per_iter"))
+                .and_then(|x| x.as_f64());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use crate::json::JsonValue;
+use crate::parse::CompileError;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The kind of target a test binary was built from, taken from cargo's compiler-artifact messages.
+#[derive(Clone, PartialEq, Debug)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    /// An integration test from the tests directory.
+    Test,
+    Bench,
+    Example,
+    /// The Doc-tests of a library.
+    Doc,
+}
+
+impl TargetKind {
+    fn from_cargo(kind: &str) -> Option<TargetKind> {
+        match kind {
+            "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" => Some(TargetKind::Lib),
+            "bin" => Some(TargetKind::Bin),
+            "test" => Some(TargetKind::Test),
+            "bench" => Some(TargetKind::Bench),
+            "example" => Some(TargetKind::Example),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TargetKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TargetKind::Lib => "lib",
+                TargetKind::Bin => "bin",
+                TargetKind::Test => "test",
+                TargetKind::Bench => "bench",
+                TargetKind::Example => "example",
+                TargetKind::Doc => "doctests",
+            }
+        )
+    }
+}
+
+/// The package and target a group of tests came from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TestTarget {
+    pub package_name: String,
+    /// The name of the target, for Doc-tests this is the name of the library.
+    pub name: String,
+    pub kind: TargetKind,
+}
+
+impl Display for TestTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            TargetKind::Doc => write!(f, "Doc-tests {}", self.name),
+            _ => write!(f, "{} {}", self.kind, self.name),
+        }
+    }
+}
+
+/// A test binary built by `cargo test --no-run`.
+#[derive(Clone, Debug)]
+pub struct TestArtifact {
+    pub package_name: String,
+    /// The name of the target, e.g. the name of the binary or the integration test file.
+    pub target_name: String,
+    pub kind: TargetKind,
+    /// The path of the target's root source file, relative to the package's manifest.
+    pub src_path: String,
+    /// The path of the compiled test binary.
+    pub executable: String,
+}
+
+impl TestArtifact {
+    pub fn target(&self) -> TestTarget {
+        TestTarget {
+            package_name: self.package_name.clone(),
+            name: self.target_name.clone(),
+            kind: self.kind.clone(),
+        }
+    }
+
+    /// Checks if this artifact is the binary shown in a Running line, e.g. `target/debug/deps/cargo_ptest-e3cae53078aa47d2`.
+    pub fn is_executable(&self, executable: &str) -> bool {
+        let file_name = |path: &str| {
+            Path::new(path.trim_end_matches(".exe"))
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+        };
+
+        file_name(&self.executable).is_some() && file_name(&self.executable) == file_name(executable)
+    }
+}
+
+/// The result of the build phase, read from the output of `cargo test --no-run --message-format=json`.
+#[derive(Clone, Debug, Default)]
+pub struct BuildInfo {
+    pub success: bool,
+    pub artifacts: Vec<TestArtifact>,
+    pub errors: Vec<CompileError>,
+    pub warnings: Vec<CompileError>,
+}
+
+impl BuildInfo {
+    /// Finds the artifact for the executable shown in a Running line.
+    pub fn artifact(&self, executable: &str) -> Option<&TestArtifact> {
+        self.artifacts.iter().find(|x| x.is_executable(executable))
+    }
+
+    /// Finds the target for the Doc-tests of a library, crate_name is the name shown in the `Doc-tests crate_name` line.
+    pub fn doc_target(&self, crate_name: &str) -> Option<TestTarget> {
+        let artifact = self.artifacts.iter().find(|x| {
+            x.kind == TargetKind::Lib && x.target_name.replace("-", "_") == crate_name
+        })?;
+
+        Some(TestTarget {
+            package_name: artifact.package_name.clone(),
+            name: crate_name.to_string(),
+            kind: TargetKind::Doc,
+        })
+    }
+}
+
+/// Gets the package name from a package id.
+/// The id looks like `path+file:///root/crate#cargo-ptest@1.3.3`, or `path+file:///root/crate/tests/integration#0.1.0` if the name is the same as the directory name.
+fn package_name(package_id: &str) -> String {
+    // old versions of cargo use the format "name version (source)"
+    if !package_id.contains('#') {
+        return package_id.split(' ').next().unwrap_or("").to_string();
+    }
+
+    let (source, fragment) = package_id.rsplit_once('#').unwrap_or(("", package_id));
+
+    match fragment.split_once('@') {
+        Some((name, _)) => name.to_string(),
+        None => source
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or("")
+            .to_string(),
+    }
+}
+
+/// Converts a diagnostic from a compiler-message into a [CompileError], the location is taken from the primary span.
+fn compile_error(message: &JsonValue, package_name: Option<String>) -> Option<CompileError> {
+    let primary_span = message
+        .get("spans")
+        .and_then(|x| x.as_array())
+        .and_then(|x| {
+            x.iter()
+                .find(|span| span.get("is_primary").and_then(|y| y.as_bool()) == Some(true))
+        });
+
+    Some(CompileError {
+        code: message
+            .get("code")
+            .and_then(|x| x.get("code"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string()),
+        message: message.get("message")?.as_str()?.to_string(),
+        file_path: primary_span
+            .and_then(|x| x.get("file_name"))
+            .and_then(|x| x.as_str())
+            .map(|x| x.to_string()),
+        line_number: primary_span
+            .and_then(|x| x.get("line_start"))
+            .and_then(|x| x.as_f64())
+            .map(|x| x as u32),
+        column: primary_span
+            .and_then(|x| x.get("column_start"))
+            .and_then(|x| x.as_f64())
+            .map(|x| x as u32),
+        package_name,
+    })
+}
+
+/// Converts a compiler-artifact message into a [TestArtifact], returns None if the artifact isn't a test binary.
+fn test_artifact(message: &JsonValue) -> Option<TestArtifact> {
+    let executable = message.get("executable")?.as_str()?;
+    let target = message.get("target")?;
+
+    // artifacts built without the test profile are normal binaries rather than test harnesses
+    if message.get("profile")?.get("test")?.as_bool() != Some(true) {
+        return None;
+    }
+
+    let manifest_path = message.get("manifest_path")?.as_str()?;
+    let src_path = target.get("src_path")?.as_str()?;
+    let relative_src_path = match Path::new(manifest_path).parent() {
+        Some(manifest_dir) => Path::new(src_path)
+            .strip_prefix(manifest_dir)
+            .unwrap_or(Path::new(src_path)),
+        None => Path::new(src_path),
+    };
+
+    Some(TestArtifact {
+        package_name: package_name(message.get("package_id")?.as_str()?),
+        target_name: target.get("name")?.as_str()?.to_string(),
+        kind: TargetKind::from_cargo(target.get("kind")?.as_array()?.first()?.as_str()?)?,
+        src_path: relative_src_path
+            .components()
+            .map(|x| x.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join("/"),
+        executable: executable.to_string(),
+    })
+}
+
+/// Parses the stdout of `cargo test --no-run --message-format=json` into a [BuildInfo].
+/// Lines that aren't JSON are ignored.
+pub fn parse_build(stdout: &str) -> BuildInfo {
+    let mut build = BuildInfo::default();
+
+    for line in stdout.lines() {
+        let message = match JsonValue::parse(line) {
+            Some(res) => res,
+            None => continue,
+        };
+
+        match message.get("reason").and_then(|x| x.as_str()) {
+            Some("compiler-artifact") => {
+                if let Some(artifact) = test_artifact(&message) {
+                    build.artifacts.push(artifact)
+                }
+            }
+            Some("compiler-message") => {
+                let package_name = message
+                    .get("package_id")
+                    .and_then(|x| x.as_str())
+                    .map(package_name);
+                let diagnostic = match message.get("message") {
+                    Some(res) => res,
+                    None => continue,
+                };
+                let error = match compile_error(diagnostic, package_name) {
+                    Some(res) => res,
+                    None => continue,
+                };
+
+                // the same diagnostic is reported for every target that includes the file, e.g. lib and lib test
+                let list = match diagnostic.get("level").and_then(|x| x.as_str()) {
+                    Some("error") => &mut build.errors,
+                    Some("warning") => &mut build.warnings,
+                    _ => continue,
+                };
+                if !list.iter().any(|x| x.to_string() == error.to_string()) {
+                    list.push(error)
+                }
+            }
+            Some("build-finished") => {
+                build.success = message.get("success").and_then(|x| x.as_bool()) == Some(true)
+            }
+            _ => {}
+        }
+    }
+
+    build
+}
+
+/// Runs `cargo metadata --no-deps`, the manifest path is taken from the args forwarded to cargo so the right workspace is used.
+/// Returns None if cargo metadata fails, e.g. there is no Cargo.toml.
+pub(crate) fn cargo_metadata(forward_args: &[String]) -> Option<JsonValue> {
+    let manifest_path = forward_args
+        .iter()
+        .position(|x| x == "--manifest-path")
+        .and_then(|x| forward_args.get(x + 1))
+        .cloned()
+        .or_else(|| {
+            forward_args
+                .iter()
+                .find_map(|x| x.strip_prefix("--manifest-path=").map(|y| y.to_string()))
+        });
+
+    let mut command = Command::new("cargo");
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(path) = manifest_path {
+        command.args(["--manifest-path", path.as_str()]);
+    }
+
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    JsonValue::parse(String::from_utf8(output.stdout).ok()?.trim())
+}
+
+/// Gets the target directory of the workspace from `cargo metadata`, see [cargo_metadata].
+pub fn metadata_target_dir(forward_args: &[String]) -> Option<PathBuf> {
+    cargo_metadata(forward_args)?
+        .get("target_directory")?
+        .as_str()
+        .map(PathBuf::from)
+}
@@ -0,0 +1,422 @@
+use crate::config::{ArgSpec, ARGS, SUBCOMMANDS};
+
+/// The shells that `cargo ptest completions` can generate a completion script for.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+}
+
+/// The names of the shells in the same order as [Shell].
+pub const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "elvish"];
+
+impl Shell {
+    /// Gets the shell from its name, e.g. `bash`.
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "elvish" => Some(Shell::Elvish),
+            _ => None,
+        }
+    }
+}
+
+/// What can be completed for the value of an option.
+enum ValueKind {
+    /// One of a fixed set of values, e.g. `<linear|tree|json|bench>`.
+    Choices(Vec<&'static str>),
+    /// A path to a file, e.g. `<file>`.
+    File,
+    /// Anything, e.g. `<seconds>`, so nothing is suggested.
+    Any,
+}
+
+/// Works out what can be completed for an option from the name of its value in [ARGS].
+fn value_kind(value: &'static str) -> ValueKind {
+    let inner = value.trim_start_matches('<').trim_end_matches('>');
+
+    if inner.contains('|') {
+        ValueKind::Choices(inner.split('|').collect())
+    } else if inner == "file" {
+        ValueKind::File
+    } else {
+        ValueKind::Any
+    }
+}
+
+/// The options that take a value along with what can be completed for it.
+fn value_args() -> Vec<(&'static ArgSpec, ValueKind)> {
+    ARGS.iter()
+        .filter_map(|x| x.value.map(|value| (x, value_kind(value))))
+        .collect()
+}
+
+/// Generates the completion script for a shell, the script completes `cargo-ptest` and is meant to be sourced or put in the shell's completions directory.
+/// The subcommands and options come from [SUBCOMMANDS] and [ARGS] so the completions match the args that are accepted.
+/// `cargo ptest` is completed by cargo's own completions which hand external subcommands over to these, zsh calls `_cargo-ptest` and bash calls the completion for `cargo-ptest` if bash-completion is 2.12 or newer.
+/// The fish and elvish completions for cargo don't do this so only `cargo-ptest` is completed in those shells.
+pub fn completions(shell: &Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+        Shell::Elvish => elvish(),
+    }
+}
+
+fn bash() -> String {
+    let mut value_cases: Vec<String> = Vec::new();
+    for (arg, kind) in value_args() {
+        let completion = match kind {
+            ValueKind::Choices(choices) => {
+                format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                    choices.join(" ")
+                )
+            }
+            ValueKind::File => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            ValueKind::Any => "COMPREPLY=()".to_string(),
+        };
+        value_cases.push(format!(
+            "        {})\n            {}\n            return 0\n            ;;",
+            arg.name, completion
+        ));
+    }
+
+    let words: Vec<&str> = SUBCOMMANDS
+        .iter()
+        .map(|x| x.0)
+        .chain(ARGS.iter().map(|x| x.name))
+        .collect();
+
+    format!(
+        r#"_cargo_ptest() {{
+    local cur prev
+    COMPREPLY=()
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    # the args after the -- are for cargo test
+    local word
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        if [[ "$word" == "--" ]]; then
+            return 0
+        fi
+    done
+
+    case "$prev" in
+{}
+        completions)
+            COMPREPLY=($(compgen -W "{}" -- "$cur"))
+            return 0
+            ;;
+        report)
+            COMPREPLY=($(compgen -f -W "{}" -- "$cur"))
+            return 0
+            ;;
+    esac
+
+    COMPREPLY=($(compgen -W "{}" -- "$cur"))
+}}
+
+complete -F _cargo_ptest cargo-ptest"#,
+        value_cases.join("\n"),
+        SHELLS.join(" "),
+        ARGS.iter().map(|x| x.name).collect::<Vec<&str>>().join(" "),
+        words.join(" ")
+    )
+}
+
+/// Escapes the description of an option for the `[...]` of `_arguments` or the `name:description` of `_describe`.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh() -> String {
+    let subcommands: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|(name, help)| format!("        '{}:{}'", name, zsh_escape(help)))
+        .collect();
+
+    let options: Vec<String> = ARGS
+        .iter()
+        .map(|x| {
+            let action = match x.value.map(value_kind) {
+                Some(ValueKind::Choices(choices)) => format!(":value:({})", choices.join(" ")),
+                Some(ValueKind::File) => ":file:_files".to_string(),
+                Some(ValueKind::Any) => format!(":{}: ", x.value.unwrap_or_default()),
+                None => "".to_string(),
+            };
+
+            format!("        '{}[{}]{}' \\", x.name, zsh_escape(x.help), action)
+        })
+        .collect();
+
+    format!(
+        r#"#compdef cargo-ptest
+
+_cargo-ptest() {{
+    local -a subcommands
+    subcommands=(
+{}
+    )
+
+    local context state state_descr line
+    _arguments -C \
+{}
+        '1: :->subcommand' \
+        '*: :->args'
+
+    case $state in
+        subcommand)
+            _describe -t subcommands 'subcommand' subcommands
+            ;;
+        args)
+            case $line[1] in
+                completions)
+                    _values 'shell' {}
+                    ;;
+                report)
+                    _files
+                    ;;
+            esac
+            ;;
+    esac
+}}
+
+_cargo-ptest "$@""#,
+        subcommands.join("\n"),
+        options.join("\n"),
+        SHELLS.join(" ")
+    )
+}
+
+/// Escapes text for a single quoted string in fish.
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish() -> String {
+    let mut lines: Vec<String> = vec!["complete -c cargo-ptest -f".to_string()];
+
+    for (name, help) in SUBCOMMANDS {
+        lines.push(format!(
+            "complete -c cargo-ptest -n '__fish_use_subcommand' -a {} -d '{}'",
+            name,
+            fish_escape(help)
+        ));
+    }
+
+    for arg in ARGS.iter() {
+        let name = match arg.name.strip_prefix("--") {
+            Some(res) => format!("-l {}", res),
+            None => format!("-s {}", arg.name.trim_start_matches('-')),
+        };
+        let value = match arg.value.map(value_kind) {
+            Some(ValueKind::Choices(choices)) => format!(" -x -a '{}'", choices.join(" ")),
+            Some(ValueKind::File) => " -r -F".to_string(),
+            Some(ValueKind::Any) => " -x".to_string(),
+            None => "".to_string(),
+        };
+
+        lines.push(format!(
+            "complete -c cargo-ptest {}{} -d '{}'",
+            name,
+            value,
+            fish_escape(arg.help)
+        ));
+    }
+
+    lines.push(format!(
+        "complete -c cargo-ptest -n '__fish_seen_subcommand_from completions' -x -a '{}'",
+        SHELLS.join(" ")
+    ));
+    lines.push("complete -c cargo-ptest -n '__fish_seen_subcommand_from report' -F".to_string());
+
+    lines.join("\n")
+}
+
+/// Quotes text as a single quoted string in elvish.
+fn elvish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn elvish() -> String {
+    let mut value_cases: Vec<String> = Vec::new();
+    for (arg, kind) in value_args() {
+        let completion = match kind {
+            ValueKind::Choices(choices) => format!("put {}", choices.join(" ")),
+            ValueKind::File => "edit:complete-filename $words[-1]".to_string(),
+            ValueKind::Any => "".to_string(),
+        };
+        value_cases.push(format!("        &{}= {{ {} }}", arg.name, completion));
+    }
+
+    let candidates: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|(name, help)| (*name, *help))
+        .chain(ARGS.iter().map(|x| (x.name, x.help)))
+        .map(|(name, help)| format!("    cand {} {}", name, elvish_quote(help)))
+        .collect();
+
+    format!(
+        r#"set edit:completion:arg-completer[cargo-ptest] = {{|@words|
+    fn cand {{|text desc|
+        edit:complex-candidate $text &display=$text' ('$desc')'
+    }}
+
+    # the args after the -- are for cargo test
+    if (has-value $words[1..-1] --) {{
+        return
+    }}
+
+    var completers = [
+{}
+        &completions= {{ put {} }}
+        &report= {{ edit:complete-filename $words[-1] }}
+    ]
+
+    var prev = $words[-2]
+    if (has-key $completers $prev) {{
+        $completers[$prev]
+        return
+    }}
+
+{}
+}}
+"#,
+        value_cases.join("\n"),
+        SHELLS.join(" "),
+        candidates.join("\n")
+    )
+}
+
+/// Escapes text for roff, backslashes and dashes are escaped and lines can't start with a `.` or `'`.
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('-', "\\-");
+
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Generates the man page for `cargo-ptest` in roff from [SUBCOMMANDS] and [ARGS], e.g. `cargo ptest man > cargo-ptest.1`.
+pub fn man_page() -> String {
+    let mut lines: Vec<String> = vec![
+        format!(
+            ".TH CARGO\\-PTEST 1 \"\" \"cargo-ptest {}\" \"User Commands\"",
+            env!("CARGO_PKG_VERSION")
+        ),
+        ".SH NAME".to_string(),
+        "cargo\\-ptest \\- prettify the output of cargo test".to_string(),
+        ".SH SYNOPSIS".to_string(),
+        "\\fBcargo ptest\\fR [\\fIsubcommand\\fR] [\\fIoptions\\fR] [\\fB\\-\\-\\fR \\fIargs for cargo test\\fR]".to_string(),
+        ".SH DESCRIPTION".to_string(),
+        "Runs \\fBcargo test\\fR and shows the results grouped by package, target and module, with the reason each failed test failed.".to_string(),
+        "Args after a \\fB\\-\\-\\fR are forwarded to cargo test.".to_string(),
+        ".SH SUBCOMMANDS".to_string(),
+    ];
+
+    for (name, help) in SUBCOMMANDS {
+        lines.push(".TP".to_string());
+        lines.push(format!("\\fB{}\\fR", roff_escape(name)));
+        lines.push(roff_escape(help));
+    }
+
+    lines.push(".SH OPTIONS".to_string());
+
+    for arg in ARGS.iter() {
+        lines.push(".TP".to_string());
+        lines.push(match arg.value {
+            Some(value) => format!(
+                "\\fB{}\\fR \\fI{}\\fR",
+                roff_escape(arg.name),
+                roff_escape(value)
+            ),
+            None => format!("\\fB{}\\fR", roff_escape(arg.name)),
+        });
+        lines.push(roff_escape(arg.help));
+    }
+
+    lines.push(".SH FILES".to_string());
+    lines.push(".TP".to_string());
+    lines.push("\\fIptest.toml\\fR".to_string());
+    lines.push("The project config, the options can also be set in the \\fB[workspace.metadata.ptest]\\fR and \\fB[package.metadata.ptest]\\fR tables of Cargo.toml.".to_string());
+    lines.push(".TP".to_string());
+    lines.push("\\fItarget/ptest/history.jsonl\\fR".to_string());
+    lines.push("The results of previous runs, shown by \\fBcargo ptest history\\fR.".to_string());
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completions, man_page, roff_escape, Shell, SHELLS};
+    use crate::config::{ARGS, SUBCOMMANDS};
+
+    /// How each shell's script names an option, fish names them without the dashes.
+    fn option_name(shell: &Shell, name: &str) -> String {
+        match (shell, name.strip_prefix("--")) {
+            (Shell::Fish, Some(res)) => format!("-l {}", res),
+            (Shell::Fish, None) => format!("-s {}", name.trim_start_matches('-')),
+            _ => name.to_string(),
+        }
+    }
+
+    #[test]
+    fn completes_every_subcommand_and_option() {
+        for shell in SHELLS.iter().map(|x| Shell::from_name(x).unwrap()) {
+            let script = completions(&shell);
+
+            for (name, _) in SUBCOMMANDS {
+                assert!(script.contains(name), "{:?} is missing {}", shell, name);
+            }
+            for arg in ARGS.iter() {
+                let name = option_name(&shell, arg.name);
+                assert!(script.contains(&name), "{:?} is missing {}", shell, name);
+
+                // the choices for a value are completed as well
+                for choice in arg.value.into_iter().filter(|x| x.contains('|')) {
+                    for choice in choice.trim_matches(['<', '>']).split('|') {
+                        assert!(script.contains(choice), "{:?} is missing {}", shell, choice);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn documents_every_subcommand_and_option() {
+        let page = man_page();
+
+        for (name, help) in SUBCOMMANDS {
+            assert!(page.contains(&format!(
+                "\\fB{}\\fR\n{}",
+                roff_escape(name),
+                roff_escape(help)
+            )));
+        }
+        for arg in ARGS.iter() {
+            assert!(
+                page.contains(&format!("\\fB{}\\fR", roff_escape(arg.name))),
+                "missing {}",
+                arg.name
+            );
+            assert!(
+                page.contains(&roff_escape(arg.help)),
+                "missing the help for {}",
+                arg.name
+            );
+        }
+    }
+}
@@ -1,6 +1,104 @@
+use crate::build::cargo_metadata;
+use crate::completions::{Shell, SHELLS};
+use crate::display::DisplayType;
+use crate::json::JsonValue;
+use crate::parse::{DocTestKind, DOC_TEST_KINDS};
+use crate::toml;
+use std::path::Path;
+
+/// What the binary should do with the output of cargo test
+#[derive(Clone, PartialEq, Debug)]
+pub enum Mode {
+    /// Run the tests and display the results
+    Run,
+    /// List the tests using `cargo test -- --list` without running them
+    List,
+    /// Run the benchmarks using `cargo bench` and display the results as a table
+    Bench,
+    /// Parse the output of a previous `cargo test` run from a file or stdin rather than running the tests
+    Report,
+    /// Show the results of previous runs, see [crate::history]
+    History,
+    /// Print a completion script for [Config::shell], see [crate::completions]
+    Completions,
+    /// Print the man page, see [crate::completions::man_page]
+    Man,
+}
+
+/// The tool used to run the tests, see [crate::run::Runner]
+#[derive(Clone, PartialEq, Debug)]
+pub enum RunnerType {
+    /// Run the tests with `cargo test`
+    Cargo,
+    /// Run the tests with `cargo nextest run`, nextest has to be installed separately
+    Nextest,
+}
+
+/// How much of the backtraces of failed tests is shown, see [Config::backtrace]
+#[derive(Clone, PartialEq, Debug)]
+pub enum BacktraceStyle {
+    /// Run the tests with `RUST_BACKTRACE=1` and only show the frames from the workspace's own crates
+    Short,
+    /// Run the tests with `RUST_BACKTRACE=full` and show every frame
+    Full,
+}
+
+impl BacktraceStyle {
+    /// The value `RUST_BACKTRACE` is set to for the tests.
+    pub fn env_value(&self) -> &'static str {
+        match self {
+            BacktraceStyle::Short => "1",
+            BacktraceStyle::Full => "full",
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub no_color: bool,
     pub debug: bool,
+    pub mode: Mode,
+    pub display_type: DisplayType,
+    /// Show the output of passed tests, --show-output is passed to the test binaries so that libtest prints it.
+    pub show_output: bool,
+    /// The name to save the results of `cargo ptest bench` under so they can be compared against later.
+    pub save_baseline: Option<String>,
+    /// The name of a saved baseline to compare the results of `cargo ptest bench` against.
+    pub baseline: Option<String>,
+    /// Tests that run for longer than this many seconds are reported as slow but are left running.
+    pub timeout: Option<u64>,
+    /// Tests that run for longer than this many seconds are marked as timed out and the test binary is killed.
+    pub hard_timeout: Option<u64>,
+    /// The tool used to run the tests when the mode is [Mode::Run].
+    pub runner: RunnerType,
+    /// Rerun the tests every time a file in the workspace changes, see [crate::watch::watch].
+    pub watch: bool,
+    /// When watching, only rerun the tests that failed in the previous run until they all pass.
+    pub rerun_failed: bool,
+    /// Browse the results in an interactive terminal UI, see [crate::tui::tui].
+    pub tui: bool,
+    /// Write the display to this file instead of printing it, colour codes are removed.
+    pub output: Option<String>,
+    /// The file to read the output of `cargo test` from when the mode is [Mode::Report], stdin is read if this is None.
+    pub report_file: Option<String>,
+    /// Print the help for ptest's own args.
+    pub help: bool,
+    /// Skip lines that can't be parsed and show them as warnings rather than failing, see [crate::parse::ParsedTestGroup::warnings].
+    pub lenient: bool,
+    /// Set `RUST_BACKTRACE` for the tests so failed tests have a backtrace, see [crate::parse::ParsedTest::backtrace].
+    pub backtrace: Option<BacktraceStyle>,
+    /// Only show the Doc-tests of this kind, e.g. `--doc-kind compile_fail`, see [crate::parse::filter_doc_kind].
+    pub doc_kind: Option<DocTestKind>,
+    /// The shell to print a completion script for when the mode is [Mode::Completions].
+    pub shell: Option<Shell>,
+    /// The number of times failed tests are rerun, tests that pass on a retry are marked as flaky.
+    pub retries: u32,
+    /// The profile from the project config to use, see [project_config].
+    pub profile: Option<String>,
+    /// Extra reports written to files after the tests have run, each is the display type and the path to write it to.
+    pub reporters: Vec<(DisplayType, String)>,
+    /// Args from the project config that are forwarded to cargo test along with the ones given after the `--`.
+    pub args: Vec<String>,
 }
 
 impl Default for Config {
@@ -8,28 +106,626 @@ impl Default for Config {
         Config {
             no_color: false,
             debug: false,
+            mode: Mode::Run,
+            display_type: DisplayType::Linear,
+            show_output: false,
+            save_baseline: None,
+            baseline: None,
+            timeout: None,
+            hard_timeout: None,
+            runner: RunnerType::Cargo,
+            watch: false,
+            rerun_failed: false,
+            tui: false,
+            output: None,
+            report_file: None,
+            help: false,
+            lenient: false,
+            doc_kind: None,
+            backtrace: None,
+            shell: None,
+            retries: 0,
+            profile: None,
+            reporters: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// An option that ptest accepts, the table of these is used both to parse the args and to generate the help.
+pub struct ArgSpec {
+    pub name: &'static str,
+    /// The name of the value the option takes, e.g. `<seconds>`, None for flags.
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+/// The subcommands and their help, the first arg that isn't an option is the subcommand, `run` is used if there isn't one.
+pub const SUBCOMMANDS: [(&str, &str); 7] = [
+    ("run", "run the tests with `cargo test` and display the results (default)"),
+    ("list", "list the tests with `cargo test -- --list` rather than running them"),
+    ("bench", "run the benchmarks with `cargo bench` and display them as a table"),
+    ("report", "display the output of a previous `cargo test` run read from [file] or stdin"),
+    ("history", "show the results of previous runs"),
+    ("completions", "print a completion script for <bash|zsh|fish|elvish>"),
+    ("man", "print the man page"),
+];
+
+/// The options ptest accepts, they have to be before the `--`.
+pub const ARGS: [ArgSpec; 22] = [
+    ArgSpec {
+        name: "--format",
+        value: Some("<linear|tree|json|bench>"),
+        help: "how the results are displayed, defaults to linear or bench for `cargo ptest bench`",
+    },
+    ArgSpec {
+        name: "--tree",
+        value: None,
+        help: "the same as --format tree",
+    },
+    ArgSpec {
+        name: "--json",
+        value: None,
+        help: "the same as --format json",
+    },
+    ArgSpec {
+        name: "--output",
+        value: Some("<file>"),
+        help: "write the results to a file instead of printing them",
+    },
+    ArgSpec {
+        name: "--show-output",
+        value: None,
+        help: "show the output of passed tests, forwards --show-output to the test binaries",
+    },
+    ArgSpec {
+        name: "--no-color",
+        value: None,
+        help: "disable coloured output",
+    },
+    ArgSpec {
+        name: "--lenient",
+        value: None,
+        help: "skip lines that can't be parsed and show them as warnings rather than failing",
+    },
+    ArgSpec {
+        name: "--doc-kind",
+        value: Some("<kind>"),
+        help: "only show the Doc-tests of a kind, one of run, no_run, compile_fail, should_panic or ignore",
+    },
+    ArgSpec {
+        name: "--backtrace",
+        value: Some("<short|full>"),
+        help: "run the tests with RUST_BACKTRACE set, short only shows the frames from the workspace's crates",
+    },
+    ArgSpec {
+        name: "--debug",
+        value: None,
+        help: "print the raw output of cargo test and information about how it was parsed",
+    },
+    ArgSpec {
+        name: "--save-baseline",
+        value: Some("<name>"),
+        help: "save the results of `cargo ptest bench` so they can be compared against later",
+    },
+    ArgSpec {
+        name: "--baseline",
+        value: Some("<name>"),
+        help: "compare the results of `cargo ptest bench` against a saved baseline",
+    },
+    ArgSpec {
+        name: "--timeout",
+        value: Some("<seconds>"),
+        help: "report tests that have been running for longer than this",
+    },
+    ArgSpec {
+        name: "--hard-timeout",
+        value: Some("<seconds>"),
+        help: "kill the test binary if a test runs for longer than this and mark the test as timed out",
+    },
+    ArgSpec {
+        name: "--runner",
+        value: Some("<cargo|nextest>"),
+        help: "run the tests with `cargo test` (the default) or `cargo nextest run`",
+    },
+    ArgSpec {
+        name: "--retries",
+        value: Some("<count>"),
+        help: "rerun failed tests up to this many times, tests that pass on a retry are marked as flaky",
+    },
+    ArgSpec {
+        name: "--profile",
+        value: Some("<name>"),
+        help: "use a profile from ptest.toml or the package.metadata.ptest table in Cargo.toml",
+    },
+    ArgSpec {
+        name: "--watch",
+        value: None,
+        help: "rerun the tests every time a file in the workspace changes",
+    },
+    ArgSpec {
+        name: "--rerun-failed",
+        value: None,
+        help: "when watching, only rerun the tests that failed in the previous run",
+    },
+    ArgSpec {
+        name: "--tui",
+        value: None,
+        help: "browse the results in an interactive terminal UI",
+    },
+    ArgSpec {
+        name: "--help",
+        value: None,
+        help: "print this help, pass --help after the -- to also see the help for cargo test",
+    },
+    ArgSpec {
+        name: "-h",
+        value: None,
+        help: "the same as --help",
+    },
+];
+
+/// Generates the help for ptest's subcommands and options from [SUBCOMMANDS] and [ARGS].
+pub fn help() -> String {
+    let mut lines: Vec<String> = vec![
+        "Prettify the output of cargo test".to_string(),
+        "".to_string(),
+        "Usage: cargo ptest [subcommand] [options] [-- <args for cargo test>]".to_string(),
+        "".to_string(),
+        "Subcommands:".to_string(),
+    ];
+
+    let subcommand_width = SUBCOMMANDS.iter().map(|x| x.0.len()).max().unwrap_or(0);
+    for (name, help) in SUBCOMMANDS {
+        lines.push(format!("   {:<subcommand_width$}  {}", name, help));
+    }
+
+    lines.push("".to_string());
+    lines.push("Options:".to_string());
+
+    let usages: Vec<String> = ARGS
+        .iter()
+        .map(|x| match x.value {
+            Some(value) => format!("{} {}", x.name, value),
+            None => x.name.to_string(),
+        })
+        .collect();
+    let option_width = usages.iter().map(|x| x.len()).max().unwrap_or(0);
+
+    for (usage, arg) in usages.iter().zip(ARGS.iter()) {
+        lines.push(format!("   {:<option_width$}  {}", usage, arg.help));
+    }
+
+    lines.push("".to_string());
+    lines.push("Args after a -- are forwarded to cargo test, e.g. `cargo ptest --tree -- --workspace -- --nocapture`".to_string());
+
+    lines.join("\n")
+}
+
+/// Parses the value of an arg that takes a number of seconds.
+fn seconds(arg: &str, value: &str) -> Result<Option<u64>, String> {
+    match value.parse::<u64>() {
+        Ok(res) => Ok(Some(res)),
+        Err(_) => Err(format!(
+            "Invalid value for argument {}, expected a number of seconds but got {}",
+            arg, value
+        )),
+    }
+}
+
+/// Parses the name of a display type, used by --format and the reporters in the project config.
+fn display_type(arg: &str, value: &str) -> Result<DisplayType, String> {
+    match value {
+        "linear" => Ok(DisplayType::Linear),
+        "tree" => Ok(DisplayType::Tree),
+        "json" => Ok(DisplayType::Json),
+        "bench" => Ok(DisplayType::Bench),
+        _ => Err(format!(
+            "Invalid value for argument {}, expected linear, tree, json or bench but got {}",
+            arg, value
+        )),
+    }
+}
+
+/// Sets the field of the config for an option that takes a value.
+fn set_value(config: &mut Config, arg: &str, value: &str) -> Result<(), String> {
+    match arg {
+        "--format" => config.display_type = display_type(arg, value)?,
+        "--retries" => {
+            config.retries = match value.parse::<u32>() {
+                Ok(res) => res,
+                Err(_) => {
+                    return Err(format!(
+                        "Invalid value for argument --retries, expected a number but got {}",
+                        value
+                    ));
+                }
+            }
+        }
+        "--profile" => config.profile = Some(value.to_string()),
+        "--output" => config.output = Some(value.to_string()),
+        "--doc-kind" => {
+            config.doc_kind = match DocTestKind::from_name(value) {
+                Some(res) => Some(res),
+                None => {
+                    return Err(format!(
+                        "Invalid value for argument --doc-kind, expected {} but got {}",
+                        DOC_TEST_KINDS.join(", "),
+                        value
+                    ));
+                }
+            }
+        }
+        "--backtrace" => {
+            config.backtrace = match value {
+                "short" => Some(BacktraceStyle::Short),
+                "full" => Some(BacktraceStyle::Full),
+                _ => {
+                    return Err(format!(
+                        "Invalid value for argument --backtrace, expected short or full but got {}",
+                        value
+                    ));
+                }
+            }
+        }
+        "--save-baseline" => config.save_baseline = Some(value.to_string()),
+        "--baseline" => config.baseline = Some(value.to_string()),
+        "--timeout" => config.timeout = seconds(arg, value)?,
+        "--hard-timeout" => config.hard_timeout = seconds(arg, value)?,
+        "--runner" => {
+            config.runner = match value {
+                "cargo" => RunnerType::Cargo,
+                "nextest" => RunnerType::Nextest,
+                _ => {
+                    return Err(format!(
+                        "Invalid value for argument --runner, expected cargo or nextest but got {}",
+                        value
+                    ));
+                }
+            }
         }
+        _ => {}
     }
+
+    Ok(())
 }
 
-const VALID_ARGS: [&str; 2] = ["--no-color", "--debug"];
+/// Sets the field of the config for a flag, flags are always true on the command line but can be turned off in the project config.
+fn set_flag(config: &mut Config, arg: &str, value: bool) {
+    match arg {
+        "--tree" if value => config.display_type = DisplayType::Tree,
+        "--json" if value => config.display_type = DisplayType::Json,
+        "--show-output" => config.show_output = value,
+        "--no-color" => config.no_color = value,
+        "--debug" => config.debug = value,
+        "--lenient" => config.lenient = value,
+        "--watch" => config.watch = value,
+        "--rerun-failed" => config.rerun_failed = value,
+        "--tui" => config.tui = value,
+        "--help" | "-h" => config.help = value,
+        _ => {}
+    }
+}
 
+/// Parses ptest's own args, the args before the `--`, into a [Config].
+/// Options that take a value accept it as the next arg or after an `=`, e.g. `--format tree` or `--format=tree`.
 pub fn config(args: Vec<String>) -> Result<Config, String> {
-    let mut config: Config = Default::default();
-    let mut args_to_find = 0;
+    parse_args(Default::default(), args)
+}
+
+/// Parses the args in the same way as [config] but on top of an existing config, so the args override it.
+fn parse_args(mut config: Config, args: Vec<String>) -> Result<Config, String> {
+    let mut subcommand: Option<String> = None;
+    let mut args = args.into_iter();
 
-    for i in args.iter() {
-        if args_to_find == 0 && !VALID_ARGS.contains(&i.clone().as_str()) {
-            return Err(format!("Invalid argument {}", i));
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with('-') => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        if let Some(spec) = ARGS.iter().find(|x| x.name == name) {
+            match (spec.value, inline_value) {
+                (Some(_), Some(value)) => set_value(&mut config, &name, &value)?,
+                (Some(_), None) => match args.next() {
+                    Some(value) => set_value(&mut config, &name, &value)?,
+                    None => return Err(format!("Missing value for argument {}", name)),
+                },
+                (None, Some(_)) => return Err(format!("Argument {} doesn't take a value", name)),
+                (None, None) => set_flag(&mut config, &name, true),
+            }
+            continue;
         }
 
-        if i == "--no-color" {
-            config.no_color = true;
+        if name.starts_with('-') {
+            return Err(format!("Invalid argument {}, see cargo ptest --help", arg));
         }
 
-        if i == "--debug" {
-            config.debug = true;
+        match subcommand {
+            None if SUBCOMMANDS.iter().any(|x| x.0 == arg) => {
+                config.mode = match arg.as_str() {
+                    "list" => Mode::List,
+                    "bench" => Mode::Bench,
+                    "report" => Mode::Report,
+                    "history" => Mode::History,
+                    "completions" => Mode::Completions,
+                    "man" => Mode::Man,
+                    _ => Mode::Run,
+                };
+                subcommand = Some(arg);
+            }
+            Some(ref res) if res == "report" && config.report_file.is_none() => {
+                config.report_file = Some(arg);
+            }
+            Some(ref res) if res == "completions" && config.shell.is_none() => {
+                config.shell = match Shell::from_name(&arg) {
+                    Some(res) => Some(res),
+                    None => {
+                        return Err(format!(
+                            "Invalid shell {}, expected {}",
+                            arg,
+                            SHELLS.join(", ")
+                        ));
+                    }
+                };
+            }
+            _ => return Err(format!("Invalid argument {}, see cargo ptest --help", arg)),
         }
     }
+
+    if config.mode == Mode::Completions && config.shell.is_none() && !config.help {
+        return Err(format!(
+            "Missing shell for cargo ptest completions, expected {}",
+            SHELLS.join(", ")
+        ));
+    }
+
+    // benchmarks are shown as a table unless another display type was asked for
+    if config.mode == Mode::Bench && config.display_type == DisplayType::Linear {
+        config.display_type = DisplayType::Bench;
+    }
+
     Ok(config)
 }
+
+/// The file the project config is read from, it is looked for in the current directory and then the root of the workspace.
+const PROJECT_FILE: &str = "ptest.toml";
+
+/// Options that only make sense on the command line so can't be set in the project config.
+/// --watch and --tui would stop a plain `cargo ptest` from ever finishing, e.g. in CI, and --rerun-failed only applies to --watch.
+const CLI_ONLY: [&str; 8] = [
+    "--help",
+    "-h",
+    "--profile",
+    "--tree",
+    "--json",
+    "--watch",
+    "--tui",
+    "--rerun-failed",
+];
+
+/// Applies a table from the project config, e.g. the root of ptest.toml or one of its profiles.
+/// The keys are the names of ptest's options without the `--`, e.g. `format = "tree"` or `show-output = true`, along with:
+/// * `color`, false is the same as `no-color = true`
+/// * `args`, an array of args to forward to cargo test
+/// * `reporters`, a table of display types and the files to write them to, e.g. `json = "target/ptest/report.json"`
+fn apply_settings(config: &mut Config, settings: &JsonValue) -> Result<(), String> {
+    let entries = match settings {
+        JsonValue::Object(res) => res,
+        _ => return Err("expected a table".to_string()),
+    };
+
+    for (key, value) in entries {
+        match (key.as_str(), value) {
+            ("profile", _) => {}
+            ("color", JsonValue::Bool(res)) => config.no_color = !res,
+            ("args", JsonValue::Array(values)) => {
+                for value in values {
+                    match value.as_str() {
+                        Some(res) => config.args.push(res.to_string()),
+                        None => return Err("args has to be an array of strings".to_string()),
+                    }
+                }
+            }
+            ("reporters", JsonValue::Object(reporters)) => {
+                for (name, path) in reporters {
+                    match path.as_str() {
+                        Some(res) => config
+                            .reporters
+                            .push((display_type("reporters", name)?, res.to_string())),
+                        None => return Err(format!("the path for the {} reporter has to be a string", name)),
+                    }
+                }
+            }
+            (_, value) => {
+                let name = format!("--{}", key);
+                let spec = match ARGS.iter().find(|x| x.name == name) {
+                    Some(res) if !CLI_ONLY.contains(&res.name) => res,
+                    _ => return Err(format!("unknown key {}", key)),
+                };
+
+                match (spec.value, value) {
+                    (Some(_), JsonValue::String(res)) => set_value(config, &name, res)?,
+                    (Some(_), JsonValue::Number(res)) => set_value(config, &name, &res.to_string())?,
+                    (None, JsonValue::Bool(res)) => set_flag(config, &name, *res),
+                    (Some(_), _) => return Err(format!("{} has to be a string or a number", key)),
+                    (None, _) => return Err(format!("{} has to be true or false", key)),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the project config, either ptest.toml or the `[workspace.metadata.ptest]` and `[package.metadata.ptest]` tables in Cargo.toml.
+/// Returns the name of where each table was found, for errors, and the table. The workspace table comes first so that the package table overrides it.
+fn find_settings(forward_args: &[String]) -> Result<Vec<(String, JsonValue)>, String> {
+    let read = |path: &Path| -> Result<Option<(String, JsonValue)>, String> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let name = path.display().to_string();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(res) => res,
+            Err(e) => return Err(format!("Could not read {}: {}", name, e)),
+        };
+
+        match toml::parse(&contents) {
+            Ok(res) => Ok(Some((name, res))),
+            Err(err) => Err(format!("Could not parse {}: {}", name, err)),
+        }
+    };
+
+    if let Some(res) = read(Path::new(PROJECT_FILE))? {
+        return Ok(vec![res]);
+    }
+
+    let metadata = match cargo_metadata(forward_args) {
+        Some(res) => res,
+        None => return Ok(Vec::new()),
+    };
+
+    if let Some(root) = metadata.get("workspace_root").and_then(|x| x.as_str())
+        && let Some(res) = read(&Path::new(root).join(PROJECT_FILE))?
+    {
+        return Ok(vec![res]);
+    }
+
+    let mut settings: Vec<(String, JsonValue)> = Vec::new();
+
+    if let Some(table) = metadata.get("metadata").and_then(|x| x.get("ptest")) {
+        settings.push(("[workspace.metadata.ptest]".to_string(), table.clone()));
+    }
+
+    // the package table is only used for the package in the current directory
+    let current_dir = std::env::current_dir().ok();
+    let package = metadata
+        .get("packages")
+        .and_then(|x| x.as_array())
+        .into_iter()
+        .flatten()
+        .find(|x| {
+            x.get("manifest_path")
+                .and_then(|y| y.as_str())
+                .and_then(|y| Path::new(y).parent())
+                .is_some_and(|y| Some(y.to_path_buf()) == current_dir)
+        });
+
+    if let Some(table) = package.and_then(|x| x.get("metadata")).and_then(|x| x.get("ptest")) {
+        settings.push(("[package.metadata.ptest]".to_string(), table.clone()));
+    }
+
+    Ok(settings)
+}
+
+/// Combines the args from the project config with the args given after the `--`, the args for cargo and the args for the test binaries are combined separately.
+fn merge_args(config_args: &[String], forward_args: Vec<String>) -> Vec<String> {
+    if config_args.is_empty() {
+        return forward_args;
+    }
+
+    let split = |args: &[String]| -> (Vec<String>, Option<Vec<String>>) {
+        match args.iter().position(|x| x == "--") {
+            Some(res) => (args[..res].to_vec(), Some(args[res + 1..].to_vec())),
+            None => (args.to_vec(), None),
+        }
+    };
+
+    let (mut cargo_args, config_test_args) = split(config_args);
+    let (forward_cargo_args, forward_test_args) = split(&forward_args);
+    cargo_args.extend(forward_cargo_args);
+
+    let mut test_args: Option<Vec<String>> = None;
+    for args in [config_test_args, forward_test_args].into_iter().flatten() {
+        test_args.get_or_insert_with(Vec::new).extend(args);
+    }
+
+    if let Some(test_args) = test_args {
+        cargo_args.push("--".to_string());
+        cargo_args.extend(test_args);
+    }
+
+    cargo_args
+}
+
+/// Parses ptest's args in the same way as [config] but on top of the project config, the args for cargo test are combined with the ones from the project config.
+/// The project config is read from ptest.toml in the current directory or the root of the workspace, or if there isn't one from the `[workspace.metadata.ptest]` and `[package.metadata.ptest]` tables in Cargo.toml.
+/// The root of the project config is applied first, then the profile chosen with --profile from its `[profile.<name>]` table and then the args.
+/// ``` toml
+/// format = "tree"
+/// timeout = 60
+/// args = ["--workspace"]
+///
+/// [profile.ci]
+/// format = "json"
+/// retries = 2
+/// reporters = { json = "target/ptest/report.json" }
+/// ```
+pub fn project_config(
+    args: Vec<String>,
+    forward_args: Vec<String>,
+) -> Result<(Config, Vec<String>), String> {
+    // the args are parsed first to find the profile and so that invalid args are reported before the project config is looked for
+    let profile = config(args.clone())?.profile;
+
+    let mut base: Config = Default::default();
+    let mut found_profile = false;
+
+    for (source, settings) in find_settings(&forward_args)? {
+        apply_settings(&mut base, &settings).map_err(|x| format!("Invalid project config in {}: {}", source, x))?;
+
+        if let Some(name) = &profile
+            && let Some(profile_settings) = settings.get("profile").and_then(|x| x.get(name))
+        {
+            found_profile = true;
+            apply_settings(&mut base, profile_settings).map_err(|x| {
+                format!("Invalid profile {} in {}: {}", name, source, x)
+            })?;
+        }
+    }
+
+    if let Some(name) = &profile
+        && !found_profile
+    {
+        return Err(format!("Could not find the profile {} in the project config", name));
+    }
+
+    let config = parse_args(base, args)?;
+    let forward_args = merge_args(&config.args, forward_args);
+
+    Ok((config, forward_args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_settings, Config};
+    use crate::display::DisplayType;
+    use crate::toml;
+
+    fn apply(input: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        apply_settings(&mut config, &toml::parse(input)?)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn applies_project_settings() {
+        let config = apply("format = \"tree\"\nshow-output = true\nretries = 2").unwrap();
+
+        assert!(config.display_type == DisplayType::Tree);
+        assert!(config.show_output);
+        assert_eq!(config.retries, 2);
+    }
+
+    #[test]
+    fn rejects_command_line_only_keys() {
+        for key in ["watch", "tui", "rerun-failed", "help", "tree"] {
+            assert_eq!(
+                apply(&format!("{} = true", key)).err(),
+                Some(format!("unknown key {}", key))
+            );
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use crate::parse::{GeneralTestType, ParsedTestGroup, Status, Summary};
+use crate::build::TargetKind;
+use crate::json::{self, ToJson};
+use crate::parse::{
+    split_backtrace, BenchResult, CompileError, Crash, DocTestKind, FailureKind, GeneralTestType,
+    ParseError, ParsedTest, ParsedTestGroup, Status, Summary, TestOutcome,
+};
+use regex::Regex;
 use std::ops::Add;
 
 pub trait Colourise: ToString {
@@ -10,6 +16,8 @@ pub trait Colourise: ToString {
     fn yellow(&self) -> String;
     /// Colour the output to be blue using ANSI codes.
     fn blue(&self) -> String;
+    /// Make the output bold using ANSI codes.
+    fn bold(&self) -> String;
 }
 
 impl Colourise for &str {
@@ -28,6 +36,10 @@ impl Colourise for &str {
     fn blue(&self) -> String {
         String::from("\x1b[34m") + &*self.to_string() + "\x1b[0m"
     }
+
+    fn bold(&self) -> String {
+        String::from("\x1b[1m") + &*self.to_string() + "\x1b[0m"
+    }
 }
 
 impl Colourise for String {
@@ -46,6 +58,10 @@ impl Colourise for String {
     fn blue(&self) -> String {
         String::from("\x1b[34m") + self + "\x1b[0m"
     }
+
+    fn bold(&self) -> String {
+        String::from("\x1b[1m") + self + "\x1b[0m"
+    }
 }
 
 pub enum Colour {
@@ -55,13 +71,16 @@ pub enum Colour {
 }
 
 /// Corresponds to the display function defined in [Display]
+#[derive(Clone, PartialEq, Debug)]
 pub enum DisplayType {
     /// Outputs data as a tree, grouped by crate -> file -> module -> etc
     Tree,
     /// Outputs data without any indentation or grouping
     Linear,
-    /// WIP Outputs data as in JSON for easy consumption by other tools.
+    /// Outputs data as in JSON for easy consumption by other tools.
     Json,
+    /// Outputs the benchmarks as a table, used by default when running `cargo ptest bench`
+    Bench,
 }
 
 pub enum Pipes {
@@ -135,19 +154,81 @@ impl StringBuilder {
     }
 }
 
-/// Takes the output from [parse::parse] and handles displaying it to the console in different forms.
+/// Takes the output from [parse::parse_combined] and handles displaying it to the console in different forms.
 pub struct Display {
     /// Displayed at the top of the output
     initial_message: String,
-    /// This is the output taken from [parse::parse]
+    /// This is the output taken from [parse::parse_combined]
     test_groups: Vec<ParsedTestGroup>,
+    /// The errors from rustc if the tests failed to build, shown instead of the tests.
+    build_errors: Vec<CompileError>,
+    /// Show the output of each test under it in the linear and tree displays.
+    show_output: bool,
+    /// The status of each test in a previous run, keyed by [test_key], tests whose status changed are highlighted.
+    previous: Option<Vec<(String, Status)>>,
+    /// Show every frame of the backtraces of failed tests rather than only the frames from the workspace, see [reason_lines].
+    full_backtrace: bool,
 }
 
 impl Display {
-    pub fn new(initial_message: &str, parsed: Vec<ParsedTestGroup>) -> Display {
+    /// Accepts either the [TestOutcome] from [parse::parse_combined] or just the list of [ParsedTestGroup]s.
+    pub fn new<T: Into<TestOutcome>>(initial_message: &str, parsed: T) -> Display {
+        let (test_groups, build_errors) = match parsed.into() {
+            TestOutcome::Tests(res) => (res, Vec::new()),
+            TestOutcome::BuildFailed(res) => (Vec::new(), res),
+        };
+
         Display {
             initial_message: initial_message.to_string(),
-            test_groups: parsed,
+            test_groups,
+            build_errors,
+            show_output: false,
+            previous: None,
+            full_backtrace: false,
+        }
+    }
+
+    /// Sets whether the output captured for each test is shown in the linear and tree displays, the JSON display always includes it.
+    pub fn show_output(&mut self, show_output: bool) {
+        self.show_output = show_output;
+    }
+
+    /// Sets whether every frame of a backtrace is shown in the failures of the linear and tree displays, the JSON display always includes the full failure reason.
+    pub fn full_backtrace(&mut self, full_backtrace: bool) {
+        self.full_backtrace = full_backtrace;
+    }
+
+    /// Sets the results of a previous run, the linear and tree displays then highlight tests whose status changed since that run, e.g. ` (was FAILED)`.
+    pub fn previous(&mut self, groups: &[ParsedTestGroup]) {
+        self.previous = Some(
+            groups
+                .iter()
+                .flat_map(|group| {
+                    group
+                        .tests
+                        .iter()
+                        .map(move |test| (test_key(group, test), test.status.clone()))
+                })
+                .collect(),
+        );
+    }
+
+    /// Shown after a test if its status is different from the previous run, empty if no previous run was set.
+    fn changed_text(&self, group: &ParsedTestGroup, test: &ParsedTest) -> String {
+        let previous = match &self.previous {
+            Some(res) => res,
+            None => return "".to_string(),
+        };
+
+        let key = test_key(group, test);
+        match previous.iter().find(|x| x.0 == key) {
+            Some((_, status)) if *status == test.status => "".to_string(),
+            Some((_, status)) => format!(
+                " {} {}",
+                "(was".bold(),
+                status.colour().bold() + &")".bold()
+            ),
+            None => format!(" {}", "(new)".blue().bold()),
         }
     }
 
@@ -156,28 +237,104 @@ impl Display {
         String::from("test")
     }
 
-    /// WIP
+    /// Outputs the tests grouped by package -> target -> module, Doc-tests are also grouped by the file they are in
+    /// ``` text
+    ///Generated by ptest
+    /// ├ cargo-ptest
+    /// │ ├ bin cargo-ptest (src/main.rs)
+    /// │ │ └ tests
+    /// │ │   ├ tests
+    /// │ │   │ ├ Failed - panic - assertion failed - See reason below
+    /// │ │   │ ├ Ignored - should_panic_and_does
+    /// │ │   │ ├ Failed - should_panic_and_doesnt - did not panic - See reason below
+    /// │ │   │ └ Pass - succeed
+    /// │ │   └ tests2
+    /// │ │     ├ Failed - panic - assertion failed - See reason below
+    /// │ │     └ Pass - succeed
+    /// │ └ test integration_test (tests/integration_test.rs)
+    /// │   ├ Pass - colour_test_green
+    /// │   └ Pass - colour_test_red
+    /// └ integration
+    ///   └ Doc-tests integration
+    ///     └ tests/integration/src/lib.rs
+    ///       ├ Pass - (line 1)
+    ///       ├ attribute
+    ///       │ ├ Ignored - ignore (line 78)
+    ///       │ └ Pass - no_run (line 88)
+    ///       └ empty_doc_mod
+    ///         └ Pass - Item (line 50)
+    ///
+    /// test result: FAILED. 10 Passed; 5 Failed; 3 Ignored; 0 Measured; 0 Filtered; finished in 0.00s
+    /// ```
+    /// If the tests weren't built by [crate::run::run] then the package isn't known so the tests are grouped by crate -> file -> module instead.
     pub fn tree(&self) -> String {
-        String::new()
+        if !self.build_errors.is_empty() {
+            return self.build_failure();
+        }
+
+        let mut root = TreeNode::new("");
+
+        for group in &self.test_groups {
+            let (package, target) = target_label(group);
+
+            for test in &group.tests {
+                let mut node = root.child(&package).child(&target);
+
+                if let Some(file_path) = &test.file_path {
+                    node = node.child(file_path);
+                }
+
+                let mut components: Vec<&str> = test
+                    .module_path
+                    .split("::")
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                let name = components.pop().unwrap_or("");
+
+                for component in components {
+                    node = node.child(component);
+                }
+
+                let output = match &test.output {
+                    Some(res) if self.show_output => Some(res.clone()),
+                    _ => None,
+                };
+
+                node.leaves.push((
+                    tree_leaf(test, name) + &self.changed_text(group, test),
+                    output,
+                ));
+            }
+        }
+
+        let mut sb: StringBuilder =
+            StringBuilder::new(self.initial_message.clone() + "\n", "", "\n");
+        root.render(&mut sb, "");
+        sb.add_raw("\n");
+        sb.add_raw(self.failures());
+        sb.add_raw(self.crashes());
+        sb.add_raw(self.warnings());
+        sb.add_raw(self.footer());
+        sb.string()
     }
 
     /// Outputs all the tests without and indentation or formatting
     /// ``` text
     ///Generated by ptest
     /// ├ Ignored - tests::tests::should_panic_and_does
-    /// ├ Failed - tests::tests2::panic - See reason below
+    /// ├ Failed - tests::tests2::panic - assertion failed - See reason below
     /// ├ Pass - tests::tests2::succeed
     /// ├ Pass - tests::tests::succeed
-    /// ├ Failed - tests::tests::should_panic_and_doesnt - See reason below
-    /// ├ Failed - tests::tests::panic - See reason below
+    /// ├ Failed - tests::tests::should_panic_and_doesnt - did not panic - See reason below
+    /// ├ Failed - tests::tests::panic - assertion failed - See reason below
     /// ├ Pass - colour_test_green
     /// ├ Pass - colour_test_red
     /// ├ Ignored - submod::ignore (this is the reason)
     /// ├ Ignored - submod::ignore_without_reason
     /// ├ Pass - submod::normal_test
     /// ├ Pass - submod::panic::should_panic
-    /// ├ Failed - submod::panic::should_panic_but_didnt - See reason below
-    /// ├ Failed - submod::panic::panicked - See reason below
+    /// ├ Failed - submod::panic::should_panic_but_didnt - did not panic - See reason below
+    /// ├ Failed - submod::panic::panicked - panicked - See reason below
     /// ├ Pass - submod::panic::should_panic_without_reason
     /// ├ Pass - works
     /// ├ Pass - from_main_rs
@@ -195,91 +352,822 @@ impl Display {
     /// ├ Pass - normal_doc_mod::private_mod from tests/integration/src/lib.rs  Doc-test
     /// ├ Pass - attribute::edition2018 from tests/integration/src/lib.rs  Doc-test
     /// ├ Pass - attribute::should_compile_fail from tests/integration/src/lib.rs  Doc-test
-    /// ├ Failed - attribute::should_compile_fail_but_didnt from tests/integration/src/lib.rs - See reason below  Doc-test
+    /// ├ Failed - attribute::should_compile_fail_but_didnt from tests/integration/src/lib.rs - compiled but should not have - See reason below  Doc-test
     ///
     /// test result: FAILED. 10 Passed; 5 Failed; 3 Ignored; 0 Measured; 0 Filtered; finished in 0.00s
     /// ```
     pub fn linear(&self) -> String {
+        if !self.build_errors.is_empty() {
+            return self.build_failure();
+        }
+
         let mut sb: StringBuilder = StringBuilder::new(
             self.initial_message.clone() + "\n",
             Pipes::T.d() + " ",
             "\n",
         );
 
-        let mut total_summary: Summary = Summary::default();
-
         for group in &self.test_groups {
             for test in group.tests.clone() {
+                let changed = self.changed_text(group, &test);
+
                 if test.test_type == GeneralTestType::Normal {
-                    if test.status == Status::Passed {
-                        sb.add(format!("{} - {}", "Pass".green(), test.module_path))
-                    } else if test.status == Status::Ignored {
-                        sb.add(format!(
-                            "{} - {} {}",
+                    match test.status {
+                        Status::Passed => sb.add(format!(
+                            "{} - {}{}{}",
+                            "Pass".green(),
+                            test.module_path,
+                            notes_text(&test),
+                            changed
+                        )),
+                        Status::Ignored => sb.add(format!(
+                            "{} - {} {}{}",
                             "Ignored".yellow(),
                             test.module_path,
                             test.ignore_reason
                                 .clone()
                                 .map_or("".to_string(), |x| format!("({})", x)),
-                        ))
-                    } else if test.status == Status::Failed {
-                        sb.add(format!(
-                            "{} - {} - See reason below",
+                            changed
+                        )),
+                        Status::Failed => sb.add(format!(
+                            "{} - {} - {}{}{}",
                             "Failed".red(),
-                            test.module_path
-                        ))
+                            test.module_path,
+                            failed_text(&test),
+                            notes_text(&test),
+                            changed
+                        )),
+                        Status::TimedOut => sb.add(format!(
+                            "{} - {} - {}{}",
+                            "Timed Out".red(),
+                            test.module_path,
+                            test.error_reason.clone().unwrap_or_default(),
+                            changed
+                        )),
+                        Status::Listed => sb.add(format!(
+                            "{} - {}{}",
+                            "Listed".blue(),
+                            test.module_path,
+                            changed
+                        )),
+                        Status::Measured => sb.add(format!(
+                            "{} - {} {}{}",
+                            "Bench".blue(),
+                            test.module_path,
+                            test.bench.as_ref().map_or("".to_string(), bench_text),
+                            changed
+                        )),
                     }
                 } else {
-                    if test.status == Status::Passed {
-                        sb.add(format!(
-                            "{} - {} from {} {}",
+                    let file_path = test.file_path.clone().map_or("ERROR".to_string(), |x| x);
+
+                    match test.status {
+                        Status::Passed => sb.add(format!(
+                            "{} - {} from {}{} {}{}",
                             "Pass".green(),
                             test.module_path,
-                            test.file_path.map_or("ERROR".to_string(), |x| x),
-                            " Doc-test".blue()
-                        ))
-                    } else if test.status == Status::Ignored {
-                        sb.add(format!(
-                            "{} - {} from {} {} {}",
+                            file_path,
+                            notes_text(&test),
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                        Status::Ignored => sb.add(format!(
+                            "{} - {} from {} {} {}{}",
                             "Ignored".yellow(),
                             test.module_path,
-                            test.file_path.map_or("ERROR".to_string(), |x| x),
+                            file_path,
                             test.ignore_reason
                                 .clone()
                                 .map_or("".to_string(), |x| format!("({})", x)),
-                            " Doc-test".blue()
-                        ))
-                    } else if test.status == Status::Failed {
-                        sb.add(format!(
-                            "{} - {} from {} - See reason below {}",
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                        Status::Failed => sb.add(format!(
+                            "{} - {} from {} - {}{} {}{}",
                             "Failed".red(),
                             test.module_path,
-                            test.file_path.map_or("ERROR".to_string(), |x| x),
-                            " Doc-test".blue()
-                        ))
+                            file_path,
+                            failed_text(&test),
+                            notes_text(&test),
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                        Status::TimedOut => sb.add(format!(
+                            "{} - {} from {} - {} {}{}",
+                            "Timed Out".red(),
+                            test.module_path,
+                            file_path,
+                            test.error_reason.clone().unwrap_or_default(),
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                        Status::Listed => sb.add(format!(
+                            "{} - {} from {} (line {}) {}{}",
+                            "Listed".blue(),
+                            test.module_path,
+                            file_path,
+                            test.line_number.map_or("?".to_string(), |x| x.to_string()),
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                        Status::Measured => sb.add(format!(
+                            "{} - {} from {} {} {}{}",
+                            "Bench".blue(),
+                            test.module_path,
+                            file_path,
+                            test.bench.as_ref().map_or("".to_string(), bench_text),
+                            doc_test_badge(&test),
+                            changed
+                        )),
+                    }
+                }
+
+                if self.show_output && let Some(output) = &test.output {
+                    for line in output.lines() {
+                        sb.add_raw(format!("{}   {}\n", Pipes::Vertical.d(), line));
                     }
                 }
-            }
-            if let Some(summary) = group.summary.clone() {
-                total_summary += summary
             }
         }
         sb.add_raw("\n");
-        sb.add_raw(total_summary.to_string());
+        sb.add_raw(self.failures());
+        sb.add_raw(self.crashes());
+        sb.add_raw(self.warnings());
+        sb.add_raw(self.footer());
         sb.string()
     }
 
-    /// WIP
+    /// Outputs the test groups as JSON, the summary is the sum of the summaries of every group and is null if no group has a summary (e.g. when listing tests)
+    /// ``` text
+    /// {"message":"Generated by ptest","groups":[{"crate_name":"cargo_ptest","file_path":"src/main.rs","tests":[...],"summary":{...}}],"summary":{...}}
+    /// ```
     pub fn json(&self) -> String {
-        String::new()
+        let summary = if self.test_groups.iter().any(|x| x.summary.is_some()) {
+            self.total_summary().to_json()
+        } else {
+            "null".to_string()
+        };
+
+        format!(
+            "{{\"message\":{},\"build_errors\":[{}],\"groups\":[{}],\"summary\":{}}}",
+            json::string(&self.initial_message),
+            self.build_errors
+                .iter()
+                .map(|x| x.to_json())
+                .collect::<Vec<String>>()
+                .join(","),
+            self.test_groups
+                .iter()
+                .map(|x| x.to_json())
+                .collect::<Vec<String>>()
+                .join(","),
+            summary
+        )
+    }
+
+    /// Outputs the benchmarks as a table grouped by target, tests that aren't benchmarks are left out.
+    /// If the benchmarks were compared to a baseline then the change in ns/iter is shown in the last column.
+    /// ``` text
+    ///Generated by ptest
+    ///
+    /// lib benchp (src/lib.rs)
+    ///   benchmark          ns/iter       +/-      MB/s    change
+    ///   benches::bytes       90.78     29.67     11377    -3.20%
+    ///   benches::sum          0.84      0.30         -         -
+    ///
+    /// test result: ok. 0 Passed; 0 Failed; 1 Ignored; 2 Measured; 0 Filtered; finished in 2.25s
+    /// ```
+    pub fn bench(&self) -> String {
+        if !self.build_errors.is_empty() {
+            return self.build_failure();
+        }
+
+        let mut sb: StringBuilder =
+            StringBuilder::new(self.initial_message.clone() + "\n", "", "\n");
+
+        let columns = ["ns/iter", "+/-", "MB/s", "change"];
+        let column_width = 10;
+        let mut found_benchmarks = false;
+
+        for group in &self.test_groups {
+            let benchmarks: Vec<(&ParsedTest, &BenchResult)> = group
+                .tests
+                .iter()
+                .filter_map(|x| Some((x, x.bench.as_ref()?)))
+                .collect();
+
+            if benchmarks.is_empty() {
+                continue;
+            }
+            found_benchmarks = true;
+
+            let name_width = benchmarks
+                .iter()
+                .map(|x| x.0.module_path.len())
+                .max()
+                .unwrap_or(0)
+                .max("benchmark".len());
+
+            let (_, target) = target_label(group);
+            sb.add_raw("\n");
+            sb.add(target.bold());
+            sb.add(format!(
+                "  {:<name_width$}{}",
+                "benchmark",
+                columns
+                    .iter()
+                    .map(|x| format!("{:>column_width$}", x))
+                    .collect::<String>()
+            ));
+
+            for (test, bench) in benchmarks {
+                let change = match bench.change() {
+                    Some(change) => {
+                        let text = format!("{:>column_width$}", format!("{:+.2}%", change));
+                        let baseline = bench.baseline.unwrap_or(0.0);
+
+                        // only colour the change if it is bigger than the deviation of the benchmark
+                        if bench.ns_per_iter + bench.deviation < baseline {
+                            text.green()
+                        } else if bench.ns_per_iter - bench.deviation > baseline {
+                            text.red()
+                        } else {
+                            text
+                        }
+                    }
+                    None => format!("{:>column_width$}", "-"),
+                };
+
+                sb.add(format!(
+                    "  {:<name_width$}{:>column_width$.2}{:>column_width$.2}{:>column_width$}{}",
+                    test.module_path,
+                    bench.ns_per_iter,
+                    bench.deviation,
+                    bench.mb_per_sec.map_or("-".to_string(), |x| x.to_string()),
+                    change
+                ));
+            }
+        }
+
+        if !found_benchmarks {
+            sb.add_raw("\n");
+            sb.add("No benchmarks were run".yellow());
+        }
+
+        sb.add_raw("\n");
+        sb.add_raw(self.crashes());
+        sb.add_raw(self.warnings());
+        sb.add_raw(self.footer());
+        sb.string()
+    }
+
+    /// Shown by the linear and tree displays instead of the tests when the tests failed to build.
+    /// ``` text
+    ///Generated by ptest
+    ///
+    /// BUILD FAILED - 1 error, no tests were run
+    /// └ error[E0308]: mismatched types
+    ///     at src/lib.rs:3:23
+    /// ```
+    fn build_failure(&self) -> String {
+        let mut sb: StringBuilder =
+            StringBuilder::new(self.initial_message.clone() + "\n\n", "", "\n");
+
+        sb.add(
+            format!(
+                "BUILD FAILED - {} error{}, no tests were run",
+                self.build_errors.len(),
+                if self.build_errors.len() == 1 { "" } else { "s" }
+            )
+            .red()
+            .bold(),
+        );
+
+        for (index, error) in self.build_errors.iter().enumerate() {
+            let (pipe, indent) = if index + 1 == self.build_errors.len() {
+                (Pipes::L, "  ".to_string())
+            } else {
+                (Pipes::T, Pipes::Vertical.d() + " ")
+            };
+
+            sb.add(format!(
+                "{} {}{}: {}",
+                pipe.d(),
+                "error".red(),
+                error
+                    .code
+                    .clone()
+                    .map_or("".to_string(), |x| format!("[{}]", x).red()),
+                error.message
+            ));
+
+            if let Some(location) = error.location() {
+                sb.add(format!("{}  at {}", indent, location.blue()));
+            }
+        }
+
+        sb.string()
+    }
+
+    /// Lists the failed tests grouped by the kind of failure with the reason each one failed, shown above the warnings in the linear and tree displays.
+    /// ``` text
+    /// Failures - 2 tests failed
+    /// ├ assertion failed - 1 test
+    /// │ └ tests::assert_eq (lib kinds (src/lib.rs))
+    /// │     assertion `left == right` failed
+    /// └ did not panic - 1 test
+    ///   └ tests::did_not_panic (lib kinds (src/lib.rs))
+    ///       note: test did not panic as expected at src/lib.rs:42:8
+    /// ```
+    fn failures(&self) -> String {
+        let mut kinds: Vec<(FailureKind, Vec<(&ParsedTestGroup, &ParsedTest)>)> = Vec::new();
+
+        for group in &self.test_groups {
+            for test in group.tests.iter().filter(|x| x.status == Status::Failed) {
+                let kind = test.failure_kind.clone().unwrap_or(FailureKind::Other);
+
+                match kinds.iter_mut().find(|(x, _)| *x == kind) {
+                    Some((_, tests)) => tests.push((group, test)),
+                    None => kinds.push((kind, vec![(group, test)])),
+                }
+            }
+        }
+
+        if kinds.is_empty() {
+            return "".to_string();
+        }
+
+        let total: usize = kinds.iter().map(|(_, tests)| tests.len()).sum();
+        let mut sb: StringBuilder = StringBuilder::new(
+            format!(
+                "{}\n",
+                format!(
+                    "Failures - {} test{} failed",
+                    total,
+                    if total == 1 { "" } else { "s" }
+                )
+                .red()
+            ),
+            "",
+            "\n",
+        );
+
+        for (kind_index, (kind, tests)) in kinds.iter().enumerate() {
+            let (pipe, indent) = if kind_index + 1 == kinds.len() {
+                (Pipes::L, "  ".to_string())
+            } else {
+                (Pipes::T, Pipes::Vertical.d() + " ")
+            };
+
+            sb.add(format!(
+                "{} {} - {} test{}",
+                pipe.d(),
+                kind.to_string().red(),
+                tests.len(),
+                if tests.len() == 1 { "" } else { "s" }
+            ));
+
+            for (index, (group, test)) in tests.iter().enumerate() {
+                let (pipe, reason_indent) = if index + 1 == tests.len() {
+                    (Pipes::L, format!("{}    ", indent))
+                } else {
+                    (Pipes::T, format!("{}{}   ", indent, Pipes::Vertical.d()))
+                };
+                let (_, target) = target_label(group);
+
+                sb.add(format!(
+                    "{}{} {} ({})",
+                    indent,
+                    pipe.d(),
+                    test.name(),
+                    target.blue()
+                ));
+
+                match &test.error_reason {
+                    Some(reason) if !reason.trim().is_empty() => {
+                        for line in reason_lines(test, self.full_backtrace) {
+                            sb.add(format!("{}{}", reason_indent, line));
+                        }
+                    }
+                    _ => sb.add(format!("{}{}", reason_indent, "No failure output".yellow())),
+                }
+            }
+        }
+
+        sb.add_raw("\n");
+        sb.string()
+    }
+
+    /// Lists the test binaries that crashed before printing their summary, shown above the warnings in the linear, tree and bench displays.
+    /// ``` text
+    /// Crashed - 1 test binary crashed
+    /// └ crash lib crash (src/lib.rs): signal: 6, SIGABRT: process abort signal, 1 test didn't report a result
+    /// ```
+    fn crashes(&self) -> String {
+        let crashed: Vec<(&ParsedTestGroup, &Crash)> = self
+            .test_groups
+            .iter()
+            .filter_map(|group| group.crashed.as_ref().map(|x| (group, x)))
+            .collect();
+
+        if crashed.is_empty() {
+            return "".to_string();
+        }
+
+        let mut text = format!(
+            "{}\n",
+            format!(
+                "Crashed - {} test binar{} crashed",
+                crashed.len(),
+                if crashed.len() == 1 { "y" } else { "ies" }
+            )
+            .red()
+        );
+
+        for (index, (group, crash)) in crashed.iter().enumerate() {
+            let pipe = if index + 1 == crashed.len() {
+                Pipes::L
+            } else {
+                Pipes::T
+            };
+            let (package, target) = target_label(group);
+            let missing = match crash.missing {
+                0 => "".to_string(),
+                1 => ", 1 test didn't report a result".to_string(),
+                x => format!(", {} tests didn't report a result", x),
+            };
+
+            text += format!(
+                "{} {} {}: {}{}\n",
+                pipe.d(),
+                package,
+                target,
+                crash.status.red(),
+                missing
+            )
+            .as_str();
+        }
+
+        text + "\n"
+    }
+
+    /// Lists the lines that were skipped in lenient mode and any counts that didn't match the parsed tests, shown above the footer of the linear, tree and bench displays.
+    /// ``` text
+    /// Warnings - 1 problem found while parsing the output
+    /// └ cargo-ptest lib cargo_ptest (src/lib.rs): Could not parse the test line at line 12: "test weird"
+    /// ```
+    fn warnings(&self) -> String {
+        let warnings: Vec<(&ParsedTestGroup, &ParseError)> = self
+            .test_groups
+            .iter()
+            .flat_map(|group| group.warnings.iter().map(move |x| (group, x)))
+            .collect();
+
+        if warnings.is_empty() {
+            return "".to_string();
+        }
+
+        let mut text = format!(
+            "{}\n",
+            format!(
+                "Warnings - {} problem{} found while parsing the output",
+                warnings.len(),
+                if warnings.len() == 1 { "" } else { "s" }
+            )
+            .yellow()
+        );
+
+        for (index, (group, warning)) in warnings.iter().enumerate() {
+            let pipe = if index + 1 == warnings.len() {
+                Pipes::L
+            } else {
+                Pipes::T
+            };
+            let (package, target) = target_label(group);
+
+            text += format!("{} {} {}: {}\n", pipe.d(), package, target, warning).as_str();
+        }
+
+        text + "\n"
+    }
+
+    fn total_summary(&self) -> Summary {
+        let mut total_summary: Summary = Summary::default();
+
+        for group in &self.test_groups {
+            if let Some(summary) = group.summary.clone() {
+                total_summary += summary
+            }
+        }
+
+        total_summary
+    }
+
+    /// The line shown at the bottom of the linear and tree displays.
+    /// If none of the groups have a summary then the tests were listed rather than run, so the number of tests found is shown instead.
+    fn footer(&self) -> String {
+        if self.test_groups.iter().any(|x| x.summary.is_some()) {
+            return self.total_summary().to_string();
+        }
+
+        let tests = self.test_groups.iter().flat_map(|x| x.tests.iter());
+        let ignored = tests
+            .clone()
+            .filter(|x| x.status == Status::Ignored)
+            .count();
+
+        format!(
+            "{} tests found; {} {}",
+            tests.count(),
+            ignored,
+            "Ignored".yellow()
+        )
     }
 
     /// Calls the requested display function and prints the result
     pub fn display(&self, _type: DisplayType) {
+        println!("{}", self.render(_type))
+    }
+
+    /// Calls the requested display function and returns the result without printing it, e.g. to write it to a file.
+    pub fn render(&self, _type: DisplayType) -> String {
         match _type {
-            DisplayType::Tree => println!("{}", self.tree()),
-            DisplayType::Linear => println!("{}", self.linear()),
-            DisplayType::Json => println!("{}", self.json()),
+            DisplayType::Tree => self.tree(),
+            DisplayType::Linear => self.linear(),
+            DisplayType::Json => self.json(),
+            DisplayType::Bench => self.bench(),
+        }
+    }
+}
+
+/// Removes the ANSI colour codes added by [Colourise] or by cargo.
+pub fn strip_colour(text: &str) -> String {
+    Regex::new(r"\x1b\[[0-9;]*m")
+        .unwrap()
+        .replace_all(text, "")
+        .to_string()
+}
+
+/// Gets the package and the label of the target a group of tests came from, e.g. `("cargo-ptest", "bin cargo-ptest (src/main.rs)")`.
+/// If the target isn't known then the crate name and file path are used instead.
+pub(crate) fn target_label(group: &ParsedTestGroup) -> (String, String) {
+    match &group.target {
+        Some(res) if res.kind == TargetKind::Doc => (res.package_name.clone(), res.to_string()),
+        Some(res) => (
+            res.package_name.clone(),
+            format!("{} ({})", res, group.file_path.join("/")),
+        ),
+        None if group.file_path.is_empty() => (group.crate_name.clone(), "Doc-tests".to_string()),
+        None => (group.crate_name.clone(), group.file_path.join("/")),
+    }
+}
+
+/// Identifies a test across runs, the target and file are included as the same module path can be in several targets.
+/// The line number is only used for Doc-tests at the root of a file, so Doc-tests don't look new when the lines above them change.
+pub(crate) fn test_key(group: &ParsedTestGroup, test: &ParsedTest) -> String {
+    let (package, target) = target_label(group);
+
+    let line = match test.line_number {
+        Some(res) if test.module_path.is_empty() => res.to_string(),
+        _ => "".to_string(),
+    };
+
+    format!(
+        "{}/{}/{}/{}/{}",
+        package,
+        target,
+        test.file_path.clone().unwrap_or_default(),
+        test.module_path,
+        line
+    )
+}
+
+/// Formats the results of a benchmark, e.g. `90.78 ns/iter (+/- 29.67) = 11377 MB/s`, the change from the baseline is added if there is one.
+fn bench_text(bench: &BenchResult) -> String {
+    let mut text = format!("{:.2} ns/iter (+/- {:.2})", bench.ns_per_iter, bench.deviation);
+
+    if let Some(mb_per_sec) = bench.mb_per_sec {
+        text += format!(" = {} MB/s", mb_per_sec).as_str();
+    }
+
+    if let Some(change) = bench.change() {
+        text += format!(" ({:+.2}% from baseline)", change).as_str();
+    }
+
+    text
+}
+
+/// Shown after tests that were reported as slow or were retried, e.g. ` (running for over 60s)` or ` (flaky, passed after 1 retry)`.
+fn notes_text(test: &ParsedTest) -> String {
+    let mut text = test.slow.map_or("".to_string(), |x| {
+        format!(" (running for over {}s)", x).yellow()
+    });
+
+    if test.retries > 0 {
+        let retries = format!(
+            "{} retr{}",
+            test.retries,
+            if test.retries == 1 { "y" } else { "ies" }
+        );
+
+        text += &match test.status {
+            Status::Passed => format!(" (flaky, passed after {})", retries).yellow(),
+            _ => format!(" (still failing after {})", retries).red(),
+        };
+    }
+
+    if test.raw_output.is_some() {
+        text += &" (printed invalid UTF-8)".yellow();
+    }
+
+    text
+}
+
+/// The lines of the failure reason of a test. If it has a backtrace then only the frames from the workspace are shown unless full is set, the frames in between are collapsed into a single line.
+/// ``` text
+/// stack backtrace:
+///   ... 4 frames from std and dependencies
+///   4: cargo_ptest::tests::panic
+///        at ./src/tests.rs:8:5
+///   ... 3 frames from std and dependencies
+/// ```
+pub(crate) fn reason_lines(test: &ParsedTest, full: bool) -> Vec<String> {
+    let reason = test
+        .error_reason
+        .as_deref()
+        .unwrap_or_default()
+        .trim_matches('\n');
+
+    let (before, after) = match split_backtrace(reason) {
+        Some((before, _, after)) if !test.backtrace.is_empty() => (before, after),
+        _ => return reason.lines().map(|x| x.to_string()).collect(),
+    };
+
+    let mut lines: Vec<String> = before.iter().map(|x| x.to_string()).collect();
+    lines.push("stack backtrace:".to_string());
+
+    let mut hidden = 0;
+    for frame in &test.backtrace {
+        if !full && !frame.in_workspace {
+            hidden += 1;
+            continue;
+        }
+
+        if hidden > 0 {
+            lines.push(hidden_frames(hidden));
+            hidden = 0;
+        }
+
+        lines.push(format!("  {}: {}", frame.index, frame.function));
+        if let Some(file) = &frame.file {
+            lines.push(format!(
+                "       at {}{}{}",
+                file.clone().blue(),
+                frame.line.map_or("".to_string(), |x| format!(":{}", x)),
+                frame.column.map_or("".to_string(), |x| format!(":{}", x))
+            ));
+        }
+    }
+
+    if hidden > 0 {
+        lines.push(hidden_frames(hidden));
+    }
+
+    lines.extend(after.iter().map(|x| x.to_string()));
+    lines
+}
+
+/// The line that replaces the frames from std and dependencies in a backtrace, see [reason_lines].
+fn hidden_frames(count: usize) -> String {
+    format!(
+        "  ... {} frame{} from std and dependencies",
+        count,
+        if count == 1 { "" } else { "s" }
+    )
+    .yellow()
+}
+
+/// Shown after the name of a failed test, e.g. `assertion failed - See reason below`.
+fn failed_text(test: &ParsedTest) -> String {
+    match &test.failure_kind {
+        Some(kind) => format!("{} - See reason below", kind.to_string().red()),
+        None => "See reason below".to_string(),
+    }
+}
+
+/// The badge for the kind of a Doc-test, e.g. ` [compile_fail]`, nothing is shown for Doc-tests that are run normally or ignored as the status already shows that.
+fn doc_kind_badge(test: &ParsedTest) -> String {
+    match &test.doc_kind {
+        Some(DocTestKind::Run | DocTestKind::Ignore) | None => "".to_string(),
+        Some(kind) => format!(" [{}]", kind).yellow(),
+    }
+}
+
+/// The ` Doc-test` label at the end of a Doc-test in the linear display, followed by the badge for its kind.
+fn doc_test_badge(test: &ParsedTest) -> String {
+    " Doc-test".blue() + &doc_kind_badge(test)
+}
+
+/// Formats a test for the tree display, name is the last component of the module path
+fn tree_leaf(test: &ParsedTest, name: &str) -> String {
+    let name = match test.line_number {
+        Some(line) if name.is_empty() => format!("(line {})", line),
+        Some(line) => format!("{} (line {})", name, line),
+        None => name.to_string(),
+    } + &doc_kind_badge(test);
+
+    match test.status {
+        Status::Passed => format!("{} - {}{}", "Pass".green(), name, notes_text(test)),
+        Status::Failed => format!(
+            "{} - {} - {}{}",
+            "Failed".red(),
+            name,
+            failed_text(test),
+            notes_text(test)
+        ),
+        Status::TimedOut => format!(
+            "{} - {} - {}",
+            "Timed Out".red(),
+            name,
+            test.error_reason.clone().unwrap_or_default()
+        ),
+        Status::Ignored => format!(
+            "{} - {}{}",
+            "Ignored".yellow(),
+            name,
+            test.ignore_reason
+                .clone()
+                .map_or("".to_string(), |x| format!(" ({})", x)),
+        ),
+        Status::Listed => format!("{} - {}", "Listed".blue(), name),
+        Status::Measured => format!(
+            "{} - {} {}",
+            "Bench".blue(),
+            name,
+            test.bench.as_ref().map_or("".to_string(), bench_text)
+        ),
+    }
+}
+
+/// A node in the tree display, the tests are stored as leaves of the module they are in
+struct TreeNode {
+    name: String,
+    /// The formatted tests and the output to show under them
+    leaves: Vec<(String, Option<String>)>,
+    children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(name: &str) -> Self {
+        TreeNode {
+            name: name.to_string(),
+            leaves: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Gets the child with the given name, creating it if it doesn't exist.
+    fn child(&mut self, name: &str) -> &mut TreeNode {
+        let index = match self.children.iter().position(|x| x.name == name) {
+            Some(res) => res,
+            None => {
+                self.children.push(TreeNode::new(name));
+                self.children.len() - 1
+            }
+        };
+
+        &mut self.children[index]
+    }
+
+    /// Adds the leaves and then the children of this node to the StringBuilder, indent is added before every line.
+    fn render(&self, sb: &mut StringBuilder, indent: &str) {
+        let total = self.leaves.len() + self.children.len();
+
+        for (index, (leaf, output)) in self.leaves.iter().enumerate() {
+            let (pipe, output_indent) = if index + 1 == total {
+                (Pipes::L, format!("{}  ", indent))
+            } else {
+                (Pipes::T, format!("{}{} ", indent, Pipes::Vertical.d()))
+            };
+            sb.add(format!("{}{} {}", indent, pipe.d(), leaf));
+
+            if let Some(output) = output {
+                for line in output.lines() {
+                    sb.add(format!("{}  {}", output_indent, line));
+                }
+            }
+        }
+
+        for (index, child) in self.children.iter().enumerate() {
+            let is_last = self.leaves.len() + index + 1 == total;
+            let (pipe, next_indent) = if is_last {
+                (Pipes::L, format!("{}  ", indent))
+            } else {
+                (Pipes::T, format!("{}{} ", indent, Pipes::Vertical.d()))
+            };
+
+            sb.add(format!("{}{} {}", indent, pipe.d(), child.name));
+            child.render(sb, next_indent.as_str());
         }
     }
 }
@@ -0,0 +1,286 @@
+use crate::display::Colourise;
+use crate::json::{self, JsonValue};
+use crate::parse::{AggregateSummary, ParsedTestGroup, Status};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The number of runs shown by `cargo ptest history`, the oldest runs are left out.
+const SHOWN_RUNS: usize = 20;
+/// The number of failed tests shown under each run.
+const SHOWN_FAILURES: usize = 5;
+/// The number of runs kept in the history file, the oldest runs are removed when a run is recorded.
+const KEPT_RUNS: usize = 200;
+
+/// Gets the path the history is saved to, every run is a line in `target/ptest/history.jsonl`.
+pub fn history_path(target_dir: &Path) -> PathBuf {
+    target_dir.join("ptest").join("history.jsonl")
+}
+
+/// The results of a previous run, see [record].
+pub struct HistoryEntry {
+    /// Seconds since the unix epoch when the run finished.
+    pub time: u64,
+    /// The args that were forwarded to cargo test.
+    pub args: Vec<String>,
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    /// The number of seconds the tests took to run.
+    pub duration: f64,
+    /// The module paths of the tests that failed.
+    pub failed_tests: Vec<String>,
+}
+
+impl HistoryEntry {
+    /// Gets the entry for a run from its results.
+    pub fn new(groups: &[ParsedTestGroup], args: &[String]) -> HistoryEntry {
+        let summary = groups.to_vec().aggregate_summary();
+
+        HistoryEntry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+            args: args.to_vec(),
+            passed: summary.passed,
+            failed: summary.failed,
+            ignored: summary.ignored,
+            duration: summary.time,
+            failed_tests: groups
+                .iter()
+                .flat_map(|x| x.tests.iter())
+                .filter(|x| x.status == Status::Failed || x.status == Status::TimedOut)
+                .map(|x| x.module_path.clone())
+                .collect(),
+        }
+    }
+
+    /// ``` text
+    /// {"time":1792368147,"args":["--workspace"],"passed":23,"failed":7,"ignored":4,"duration":0.21,"failed_tests":["tests::panic"]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let strings = |values: &[String]| {
+            values
+                .iter()
+                .map(|x| json::string(x))
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+
+        format!(
+            "{{\"time\":{},\"args\":[{}],\"passed\":{},\"failed\":{},\"ignored\":{},\"duration\":{:.3},\"failed_tests\":[{}]}}",
+            self.time,
+            strings(&self.args),
+            self.passed,
+            self.failed,
+            self.ignored,
+            self.duration,
+            strings(&self.failed_tests)
+        )
+    }
+
+    /// Reads an entry from a line of the history file, returns None if the line isn't a valid entry.
+    fn from_json(value: &JsonValue) -> Option<HistoryEntry> {
+        let strings = |key: &str| -> Option<Vec<String>> {
+            value
+                .get(key)?
+                .as_array()?
+                .iter()
+                .map(|x| x.as_str().map(|y| y.to_string()))
+                .collect()
+        };
+        let number = |key: &str| value.get(key).and_then(|x| x.as_f64());
+
+        Some(HistoryEntry {
+            time: number("time")? as u64,
+            args: strings("args")?,
+            passed: number("passed")? as u32,
+            failed: number("failed")? as u32,
+            ignored: number("ignored")? as u32,
+            duration: number("duration")?,
+            failed_tests: strings("failed_tests")?,
+        })
+    }
+}
+
+/// Adds a run to the end of the history file at path, the file and its directory are created if they don't exist.
+/// Only the last [KEPT_RUNS] runs are kept so the file doesn't grow forever.
+pub fn record(entry: &HistoryEntry, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        return Err(format!("Could not create {}: {}", parent.display(), e));
+    }
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Could not read history {}: {}", path.display(), e)),
+    };
+
+    let json = entry.to_json();
+    let mut lines: Vec<&str> = contents.lines().filter(|x| !x.trim().is_empty()).collect();
+    lines.push(&json);
+
+    let kept = lines[lines.len().saturating_sub(KEPT_RUNS)..].join("\n") + "\n";
+
+    match std::fs::write(path, kept) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!(
+            "Could not save history to {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+/// Loads every run from the history file at path, oldest first. Lines that can't be read are skipped.
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(res) => res,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Could not read history {}: {}", path.display(), e)),
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|x| JsonValue::parse(x.trim()))
+        .filter_map(|x| HistoryEntry::from_json(&x))
+        .collect())
+}
+
+/// Formats seconds since the unix epoch as a UTC date and time, e.g. `2026-10-19 00:22:27`.
+fn date(time: u64) -> String {
+    let days = (time / 86400) as i64;
+    let seconds = time % 86400;
+
+    // converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Shows the most recent runs as a table, the tests that failed are listed under each run.
+/// ``` text
+///Generated by ptest
+///
+///   date                 result  passed  failed  ignored     time  args
+///   2026-10-19 00:22:27  FAILED      23       7        4    0.21s  --workspace --no-fail-fast
+///     tests::tests2::panic, tests::tests::panic and 5 more
+/// ```
+pub fn table(initial_message: &str, entries: &[HistoryEntry]) -> String {
+    let mut lines: Vec<String> = vec![initial_message.to_string(), "".to_string()];
+
+    if entries.is_empty() {
+        lines.push(
+            "No runs have been saved yet, runs are saved by cargo ptest and cargo ptest run"
+                .yellow(),
+        );
+        return lines.join("\n");
+    }
+
+    if entries.len() > SHOWN_RUNS {
+        lines.push(format!(
+            "Showing the last {} of {} runs",
+            SHOWN_RUNS,
+            entries.len()
+        ));
+    }
+
+    lines.push(format!(
+        "   {:<19}  {:<6}  {:>6}  {:>6}  {:>7}  {:>7}  {}",
+        "date", "result", "passed", "failed", "ignored", "time", "args"
+    ));
+
+    for entry in &entries[entries.len().saturating_sub(SHOWN_RUNS)..] {
+        let result = if entry.failed > 0 {
+            format!("{:<6}", "FAILED").red()
+        } else {
+            format!("{:<6}", "ok").green()
+        };
+
+        lines.push(format!(
+            "   {}  {}  {:>6}  {:>6}  {:>7}  {:>7}  {}",
+            date(entry.time),
+            result,
+            entry.passed,
+            entry.failed,
+            entry.ignored,
+            format!("{:.2}s", entry.duration),
+            entry.args.join(" ")
+        ));
+
+        if !entry.failed_tests.is_empty() {
+            let mut failures = entry
+                .failed_tests
+                .iter()
+                .take(SHOWN_FAILURES)
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            if entry.failed_tests.len() > SHOWN_FAILURES {
+                failures +=
+                    format!(" and {} more", entry.failed_tests.len() - SHOWN_FAILURES).as_str();
+            }
+
+            lines.push(format!("     {}", failures.red()));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Shows the runs as a JSON array, see [HistoryEntry::to_json].
+pub fn json(entries: &[HistoryEntry]) -> String {
+    format!(
+        "[{}]",
+        entries
+            .iter()
+            .map(|x| x.to_json())
+            .collect::<Vec<String>>()
+            .join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, record, HistoryEntry, KEPT_RUNS};
+
+    #[test]
+    fn keeps_the_latest_runs() {
+        let path = std::env::temp_dir()
+            .join(format!("ptest-history-{}", std::process::id()))
+            .join("history.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        for run in 0..KEPT_RUNS + 5 {
+            let mut entry = HistoryEntry::new(&[], &[]);
+            entry.time = run as u64;
+            record(&entry, &path).unwrap();
+        }
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), KEPT_RUNS);
+        assert_eq!(entries[0].time, 5);
+        assert_eq!(entries[KEPT_RUNS - 1].time, KEPT_RUNS as u64 + 4);
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,434 @@
+use crate::parse::{
+    BenchResult, CompileError, Crash, Frame, LineKind, ParseError, ParsedTest, ParsedTestGroup, Status,
+    Summary,
+};
+
+/// Converts the parsed output into JSON for [crate::display::Display::json].
+pub(crate) trait ToJson {
+    fn to_json(&self) -> String;
+}
+
+/// Quotes and escapes a string so it can be used as a JSON string.
+pub(crate) fn string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            c if (c as u32) < 0x20 => escaped += format!("\\u{:04x}", c as u32).as_str(),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped + "\""
+}
+
+/// Encodes bytes as standard base64 with padding so they can be embedded in a JSON string.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let value = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, x)| acc | ((*x as u32) << (16 - 8 * i)));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((value >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Converts an optional string to a JSON string or null.
+pub(crate) fn option_string(value: &Option<String>) -> String {
+    match value {
+        Some(res) => string(res),
+        None => "null".to_string(),
+    }
+}
+
+impl ToJson for Status {
+    fn to_json(&self) -> String {
+        string(match self {
+            Status::Passed => "passed",
+            Status::Failed => "failed",
+            Status::Ignored => "ignored",
+            Status::Listed => "listed",
+            Status::Measured => "measured",
+            Status::TimedOut => "timed_out",
+        })
+    }
+}
+
+impl ToJson for Summary {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"status\":{},\"passed\":{},\"failed\":{},\"ignored\":{},\"measured\":{},\"filtered\":{},\"time\":{}}}",
+            self.status.to_json(),
+            self.passed,
+            self.failed,
+            self.ignored,
+            self.measured,
+            self.filtered,
+            self.time
+        )
+    }
+}
+
+impl ToJson for ParsedTest {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"test_type\":{},\"module_path\":{},\"status\":{},\"file_path\":{},\"line_number\":{},\"note\":{},\"doc_kind\":{},\"error_reason\":{},\"failure_kind\":{},\"backtrace\":[{}],\"ignore_reason\":{},\"output\":{},\"bench\":{},\"slow\":{},\"retries\":{},\"raw_output\":{}}}",
+            string(self.test_type.to_string().as_str()),
+            string(&self.module_path),
+            self.status.to_json(),
+            option_string(&self.file_path),
+            self.line_number
+                .map_or("null".to_string(), |x| x.to_string()),
+            option_string(&self.note),
+            option_string(&self.doc_kind.as_ref().map(|x| x.to_string())),
+            option_string(&self.error_reason),
+            option_string(&self.failure_kind.as_ref().map(|x| x.name().to_string())),
+            self.backtrace
+                .iter()
+                .map(|x| x.to_json())
+                .collect::<Vec<String>>()
+                .join(","),
+            option_string(&self.ignore_reason),
+            option_string(&self.output),
+            self.bench
+                .as_ref()
+                .map_or("null".to_string(), |x| x.to_json()),
+            self.slow.map_or("null".to_string(), |x| x.to_string()),
+            self.retries,
+            self.raw_output
+                .as_ref()
+                .map_or("null".to_string(), |x| string(&base64(x)))
+        )
+    }
+}
+
+impl ToJson for BenchResult {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"ns_per_iter\":{},\"deviation\":{},\"mb_per_sec\":{},\"baseline\":{},\"change\":{}}}",
+            self.ns_per_iter,
+            self.deviation,
+            self.mb_per_sec.map_or("null".to_string(), |x| x.to_string()),
+            self.baseline.map_or("null".to_string(), |x| x.to_string()),
+            self.change().map_or("null".to_string(), |x| x.to_string())
+        )
+    }
+}
+
+impl ToJson for Crash {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"status\":{},\"missing\":{}}}",
+            string(&self.status),
+            self.missing
+        )
+    }
+}
+
+impl ToJson for Frame {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"index\":{},\"function\":{},\"file\":{},\"line\":{},\"column\":{},\"in_workspace\":{}}}",
+            self.index,
+            string(&self.function),
+            option_string(&self.file),
+            self.line.map_or("null".to_string(), |x| x.to_string()),
+            self.column.map_or("null".to_string(), |x| x.to_string()),
+            self.in_workspace
+        )
+    }
+}
+
+impl ToJson for ParsedTestGroup {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"crate_name\":{},\"package_name\":{},\"target_name\":{},\"target_kind\":{},\"file_path\":{},\"tests\":[{}],\"summary\":{},\"output\":{},\"warnings\":[{}],\"crashed\":{}}}",
+            string(&self.crate_name),
+            option_string(&self.target.as_ref().map(|x| x.package_name.clone())),
+            option_string(&self.target.as_ref().map(|x| x.name.clone())),
+            option_string(&self.target.as_ref().map(|x| x.kind.to_string())),
+            string(self.file_path.join("/").as_str()),
+            self.tests
+                .iter()
+                .map(|x| x.to_json())
+                .collect::<Vec<String>>()
+                .join(","),
+            self.summary
+                .as_ref()
+                .map_or("null".to_string(), |x| x.to_json()),
+            option_string(&self.output),
+            self.warnings
+                .iter()
+                .map(|x| x.to_json())
+                .collect::<Vec<String>>()
+                .join(","),
+            self.crashed
+                .as_ref()
+                .map_or("null".to_string(), |x| x.to_json())
+        )
+    }
+}
+
+impl ToJson for ParseError {
+    fn to_json(&self) -> String {
+        let (kind, line, line_number) = match self {
+            ParseError::InvalidLine {
+                kind,
+                line,
+                line_number,
+            } => (
+                match kind {
+                    LineKind::Running => "running",
+                    LineKind::BlockStart => "block_start",
+                    LineKind::Test => "test",
+                    LineKind::Bench => "bench",
+                    LineKind::List => "list",
+                    LineKind::Summary => "summary",
+                },
+                Some(line.clone()),
+                *line_number,
+            ),
+            ParseError::MissingSummary { .. } => ("missing_summary", None, None),
+            ParseError::CountMismatch { .. } => ("count_mismatch", None, None),
+            ParseError::NoTests => ("no_tests", None, None),
+        };
+
+        format!(
+            "{{\"kind\":{},\"line\":{},\"line_number\":{},\"message\":{}}}",
+            string(kind),
+            option_string(&line),
+            line_number.map_or("null".to_string(), |x| x.to_string()),
+            string(&self.to_string())
+        )
+    }
+}
+
+impl ToJson for CompileError {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"code\":{},\"message\":{},\"file_path\":{},\"line_number\":{},\"column\":{},\"package_name\":{}}}",
+            option_string(&self.code),
+            string(&self.message),
+            option_string(&self.file_path),
+            self.line_number
+                .map_or("null".to_string(), |x| x.to_string()),
+            self.column.map_or("null".to_string(), |x| x.to_string()),
+            option_string(&self.package_name)
+        )
+    }
+}
+
+/// A parsed JSON value, used to read the messages from `cargo --message-format=json`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parses a complete JSON document, returns None if it isn't valid JSON.
+    pub(crate) fn parse(input: &str) -> Option<JsonValue> {
+        let mut parser = JsonParser {
+            chars: input.chars().peekable(),
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        match parser.chars.next() {
+            Some(_) => None,
+            None => Some(value),
+        }
+    }
+
+    /// Gets the value of a key if this is an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries.iter().find(|x| x.0 == key).map(|x| &x.1),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(res) => Some(res.as_str()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(res) => Some(res),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(res) => Some(*res),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(res) => Some(*res),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|x| x.is_whitespace()).is_some() {}
+    }
+
+    /// Consumes the expected string, e.g. the "true" literal.
+    fn expect(&mut self, expected: &str) -> Option<()> {
+        for c in expected.chars() {
+            self.chars.next_if_eq(&c)?;
+        }
+        Some(())
+    }
+
+    fn value(&mut self) -> Option<JsonValue> {
+        self.skip_whitespace();
+
+        match self.chars.peek()? {
+            'n' => self.expect("null").map(|_| JsonValue::Null),
+            't' => self.expect("true").map(|_| JsonValue::Bool(true)),
+            'f' => self.expect("false").map(|_| JsonValue::Bool(false)),
+            '"' => self.string().map(JsonValue::String),
+            '[' => self.array(),
+            '{' => self.object(),
+            _ => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Option<JsonValue> {
+        let mut number = String::new();
+
+        while let Some(c) = self
+            .chars
+            .next_if(|x| x.is_ascii_digit() || ['-', '+', '.', 'e', 'E'].contains(x))
+        {
+            number.push(c);
+        }
+
+        number.parse::<f64>().ok().map(JsonValue::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect("\"")?;
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(value),
+                '\\' => match self.chars.next()? {
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'b' => value.push('\u{8}'),
+                    'f' => value.push('\u{c}'),
+                    'u' => {
+                        let mut code = self.hex_code()?;
+
+                        // characters outside the basic multilingual plane are escaped as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.expect("\\u")?;
+                            let low = self.hex_code()?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.checked_sub(0xDC00)?);
+                        }
+
+                        value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn hex_code(&mut self) -> Option<u32> {
+        let mut code = String::new();
+
+        for _ in 0..4 {
+            code.push(self.chars.next()?);
+        }
+
+        u32::from_str_radix(code.as_str(), 16).ok()
+    }
+
+    fn array(&mut self) -> Option<JsonValue> {
+        self.expect("[")?;
+        let mut values: Vec<JsonValue> = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Some(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.chars.next()? {
+                ',' => continue,
+                ']' => return Some(JsonValue::Array(values)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<JsonValue> {
+        self.expect("{")?;
+        let mut entries: Vec<(String, JsonValue)> = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Some(JsonValue::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.chars.next()? {
+                ',' => continue,
+                '}' => return Some(JsonValue::Object(entries)),
+                _ => return None,
+            }
+        }
+    }
+}
@@ -4,8 +4,18 @@
 //!
 //! This project was inspired by [cargo-pretty-test](https://crates.io/crates/cargo-pretty-test)
 
+pub mod bench;
+pub mod build;
+pub mod completions;
 pub mod config;
 pub mod display;
+pub mod history;
+mod json;
 mod logger;
+pub mod nextest;
 pub mod parse;
 pub mod run;
+mod timeout;
+mod toml;
+pub mod tui;
+pub mod watch;
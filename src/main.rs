@@ -1,20 +1,190 @@
+mod bench;
+mod build;
+mod completions;
 mod config;
 mod display;
+mod history;
+mod json;
 mod logger;
+mod nextest;
 mod parse;
 mod run;
+mod timeout;
+mod toml;
+mod tui;
+mod watch;
 
-use crate::display::{Display, DisplayType};
-use crate::run::run;
+use crate::build::metadata_target_dir;
+use crate::completions::{completions, man_page};
+use crate::config::{help, BacktraceStyle, Config, Mode};
+use crate::display::{strip_colour, Colourise, Display, DisplayType};
+use crate::history::{history_path, HistoryEntry};
+use crate::logger::warning;
+use crate::parse::{Status, TestOutcome};
+use crate::run::{bench, binary_args, list, report, run};
+use crate::tui::tui;
+use crate::watch::watch;
+use std::path::Path;
+use std::process::ExitCode;
 
-fn main() {
-    match run(None, None) {
+/// The exit code cargo test uses when the tests fail to build or a test fails.
+const TESTS_FAILED: u8 = 101;
+
+/// Writes the output to a file without the colour codes, the directories it is in are created if they don't exist.
+fn write_file(path: &str, text: &str) {
+    if let Some(parent) = Path::new(path).parent()
+        && !parent.as_os_str().is_empty()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
+        eprintln!("Could not create {}: {}", parent.display(), e);
+        return;
+    }
+
+    if let Err(e) = std::fs::write(path, strip_colour(text) + "\n") {
+        eprintln!("Could not write to {}: {}", path, e);
+    }
+}
+
+/// Prints the output, or writes it to the file set by --output without the colour codes.
+fn output(cfg: &Config, text: String) {
+    match &cfg.output {
+        Some(path) => write_file(path, &text),
+        None if cfg.no_color => println!("{}", strip_colour(&text)),
+        None => println!("{}", text),
+    }
+}
+
+/// Shows the runs saved in the history, see [history::record]. Returns false if the history couldn't be read.
+fn show_history(cfg: &Config, args: &[String]) -> bool {
+    let target_dir = match metadata_target_dir(args) {
+        Some(res) => res,
+        None => {
+            eprintln!("Could not find the target directory the history is saved in");
+            return false;
+        }
+    };
+
+    match history::load(&history_path(&target_dir)) {
+        Ok(entries) if cfg.display_type == DisplayType::Json => output(cfg, history::json(&entries)),
+        Ok(entries) => output(cfg, history::table("Generated by ptest", &entries)),
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether the tests failed to build, or a test failed, timed out or crashed.
+fn failed(result: &TestOutcome) -> bool {
+    match result {
+        TestOutcome::BuildFailed(_) => true,
+        TestOutcome::Tests(groups) => groups.iter().any(|group| {
+            group.crashed.is_some()
+                || group
+                    .tests
+                    .iter()
+                    .any(|x| x.status == Status::Failed || x.status == Status::TimedOut)
+        }),
+    }
+}
+
+/// Saves the results of a run to the history so they can be seen with `cargo ptest history`.
+fn record_history(result: &TestOutcome, args: &[String]) {
+    let groups = match result {
+        TestOutcome::Tests(res) if !res.is_empty() => res,
+        _ => return,
+    };
+
+    if let Some(target_dir) = metadata_target_dir(args)
+        && let Err(err) = history::record(&HistoryEntry::new(groups, args), &history_path(&target_dir))
+    {
+        warning!("{}", err);
+    }
+}
+
+/// Exits with 101 like cargo test if the tests failed to build or a test failed, and with 1 if ptest itself failed, e.g. because of an invalid arg.
+fn main() -> ExitCode {
+    let (cfg, args) = match binary_args() {
+        Ok(res) => res,
+        Err(err) => {
+            eprintln!("{}", err.with_sources().red());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if cfg.help {
+        println!("{}", help());
+        return ExitCode::SUCCESS;
+    }
+
+    if cfg.tui {
+        if let Err(err) = tui(cfg, args) {
+            eprintln!("{}", err.with_sources().red());
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if cfg.watch {
+        watch(cfg, args);
+    }
+
+    let result = match cfg.mode {
+        Mode::Run => {
+            let result = run(Some(cfg.clone()), Some(args.clone()));
+            if let Ok(res) = &result {
+                record_history(res, &args);
+            }
+            result
+        }
+        Mode::List => list(Some(cfg.clone()), Some(args)),
+        Mode::Bench => bench(Some(cfg.clone()), Some(args)),
+        Mode::Report => report(cfg.clone()),
+        Mode::History => {
+            return if show_history(&cfg, &args) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            };
+        }
+        Mode::Completions => {
+            if let Some(shell) = &cfg.shell {
+                output(&cfg, completions(shell));
+            }
+            return ExitCode::SUCCESS;
+        }
+        Mode::Man => {
+            output(&cfg, man_page());
+            return ExitCode::SUCCESS;
+        }
+    };
+
+    match result {
         Ok(res) => {
-            let display = Display::new("Generated by ptest", res);
+            let failed = failed(&res);
+
+            let mut display = Display::new("Generated by ptest", res);
+            display.show_output(cfg.show_output);
+            display.full_backtrace(cfg.backtrace == Some(BacktraceStyle::Full));
+
+            output(&cfg, display.render(cfg.display_type.clone()));
 
-            display.display(DisplayType::Linear);
+            for (display_type, path) in cfg.reporters.iter() {
+                write_file(path, &display.render(display_type.clone()));
+            }
+
+            if failed {
+                ExitCode::from(TESTS_FAILED)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(err) => {
+            eprintln!("{}", err.with_sources().red());
+            ExitCode::FAILURE
         }
-        Err(err) => println!("{}", err),
     }
 }
 
@@ -0,0 +1,363 @@
+use crate::build::{BuildInfo, TargetKind, TestTarget};
+use crate::config::Config;
+use crate::logger::info;
+use crate::parse::{
+    build_errors, check_counts, classify_failures, parse_backtraces, GeneralTestType, ParseError, ParsedTest, ParsedTestGroup, Status, Summary,
+    TestOutcome,
+};
+use regex::Regex;
+
+/// Gets the target a nextest binary id refers to.
+/// The binary id is the package name for the library, e.g. `cargo-ptest`, and `package::name` for integration tests, e.g. `cargo-ptest::integration_test`.
+/// Other targets include their kind, e.g. `cargo-ptest::bin/cargo-ptest`.
+fn binary_target(binary_id: &str, build: Option<&BuildInfo>) -> (TestTarget, Vec<String>) {
+    let (package_name, kind, name) = match binary_id.split_once("::") {
+        None => (binary_id, TargetKind::Lib, None),
+        Some((package, rest)) => match rest.split_once('/') {
+            Some(("bin", name)) => (package, TargetKind::Bin, Some(name)),
+            Some(("bench", name)) => (package, TargetKind::Bench, Some(name)),
+            Some(("example", name)) => (package, TargetKind::Example, Some(name)),
+            _ => (package, TargetKind::Test, Some(rest)),
+        },
+    };
+
+    let artifact = build.and_then(|x| {
+        x.artifacts.iter().find(|artifact| {
+            artifact.package_name == package_name
+                && artifact.kind == kind
+                && name.is_none_or(|name| artifact.target_name == name)
+        })
+    });
+
+    match artifact {
+        Some(res) => (
+            res.target(),
+            res.src_path.split("/").map(|x| x.to_string()).collect(),
+        ),
+        None => (
+            TestTarget {
+                package_name: package_name.to_string(),
+                name: name.unwrap_or(package_name).to_string(),
+                kind,
+            },
+            Vec::new(),
+        ),
+    }
+}
+
+/// Converts the status nextest shows for a test into a [Status], the status is None for statuses that don't mean the test has finished, e.g. SLOW.
+/// Retries are prefixed with the attempt, e.g. `TRY 2 FAIL`, which is split off and returned with the status.
+/// Returns None if nextest doesn't show the status, e.g. for a line printed by a test like `ERROR [main] server started`.
+fn nextest_status(status: &str) -> Option<(Option<u32>, Option<Status>)> {
+    let (attempt, status) = match status.strip_prefix("TRY ").and_then(|x| x.split_once(' ')) {
+        Some((attempt, status)) => (attempt.parse::<u32>().ok(), status),
+        None => (None, status),
+    };
+
+    let status = match status {
+        "PASS" | "LEAK" => Some(Status::Passed),
+        "SKIP" => Some(Status::Ignored),
+        "TIMEOUT" | "TMT" => Some(Status::TimedOut),
+        "FAIL" | "LEAK-FAIL" | "ABORT" => Some(Status::Failed),
+        "SLOW" | "RETRY" | "TERMINATING" | "TRMNTG" => None,
+        // the signal that killed the test, e.g. SIGSEGV
+        _ if status.len() > 3
+            && status.starts_with("SIG")
+            && status.chars().all(|x| x.is_ascii_uppercase()) =>
+        {
+            Some(Status::Failed)
+        }
+        _ => return None,
+    };
+
+    Some((attempt, status))
+}
+
+/// Removes the blank lines nextest puts around the output of a test, returns None if there is no output left.
+fn tidy_output(output: Option<String>) -> Option<String> {
+    let output = output?;
+    let output = output.trim_start_matches('\n').trim_end();
+
+    (!output.is_empty()).then(|| output.to_string() + "\n")
+}
+
+/// Parses the output of `cargo nextest run` into [ParsedTestGroup]s, there is a group for each test binary.
+/// The output should have stdout and stderr combined in the same way as [crate::parse::parse_combined].
+/// ``` text
+///     Starting 3 tests across 2 binaries
+///         PASS [   0.004s] (1/3) cargo-ptest tests::succeed
+///         FAIL [   0.005s] (2/3) cargo-ptest tests::panic
+///   stderr ───
+///
+///     thread 'tests::panic' panicked at src/tests/tests.rs:4:5:
+///
+///         PASS [   0.003s] (3/3) cargo-ptest::integration_test parses
+/// ────────────
+///      Summary [   0.010s] 3 tests run: 2 passed, 1 failed, 0 skipped
+/// ```
+/// The stderr of a failed test is its error reason, everything else a test prints is its output.
+/// Only the last try of a retried test is kept, the tries before it are counted in [ParsedTest::retries].
+/// nextest doesn't run Doc-tests so there are never any Doc-test groups.
+pub fn parse_nextest(
+    output: String,
+    build: Option<&BuildInfo>,
+    cfg: Config,
+) -> Result<TestOutcome, ParseError> {
+    if cfg.debug {
+        info!("Output");
+        println!("{}", output);
+    }
+
+    // nextest always ends with "error: test run failed" if a test failed so the errors from rustc are only looked for if nextest never started running the tests
+    if !output.lines().any(|x| x.trim().starts_with("Starting "))
+        && let Some(errors) = build_errors(&output)
+    {
+        return Ok(TestOutcome::BuildFailed(errors));
+    }
+
+    // the counter is `(───)` for tests that haven't finished yet and tries that will be retried
+    let status_line = Regex::new(
+        r"^\s*(?<status>[A-Z][A-Z0-9 -]*?)\s+\[\s*(?<time>[^\]]*?)\s*\]\s+(\((?<counter>[^)]*)\)\s+)?(?<binary_id>\S+)\s+(?<name>.+?)\s*$",
+    )
+    .unwrap();
+    // older versions of nextest name the test in the title of its output
+    let output_title =
+        Regex::new(r"^(────|---) (?<channel>STDOUT|STDERR|OUTPUT):\s+(?<binary_id>\S+) (?<name>.+?)( ---)?\s*$")
+            .unwrap();
+    let output_header = Regex::new(r"^(?<channel>stdout|stderr|output) ─+$").unwrap();
+    let slow_time = Regex::new(r"^>\s*(?<seconds>[\d.]+)s$").unwrap();
+    let separator = Regex::new(r"^(─+|-+)$").unwrap();
+    let summary_count =
+        Regex::new(r"(?<count>\d+) (?<name>tests? run|passed|failed|timed out)").unwrap();
+
+    let mut parsed_groups: Vec<ParsedTestGroup> = Vec::new();
+    // the binary id and name of the last test that finished, its output follows the status line
+    let mut finished: Option<(String, String)> = None;
+    // the test whose output is being read and whether it is stdout
+    let mut captured: Option<(String, String, bool)> = None;
+    // the counts from the summary line, checked against the parsed tests once they are all read
+    let mut expected: Vec<(&'static str, u32)> = Vec::new();
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+
+        // the failed tests are listed again after the summary
+        if trimmed.starts_with("Summary [") {
+            // counts that are left out of the summary are 0, apart from the number of tests run which is always there
+            expected = vec![("run", 0), ("passed", 0), ("failed", 0), ("timed out", 0)];
+            for capture in summary_count.captures_iter(trimmed) {
+                let name = match &capture["name"] {
+                    "test run" | "tests run" => "run",
+                    "passed" => "passed",
+                    "failed" => "failed",
+                    _ => "timed out",
+                };
+                if let Some(count) = expected.iter_mut().find(|x| x.0 == name) {
+                    count.1 = capture["count"].parse().unwrap_or(0);
+                }
+            }
+            break;
+        }
+
+        if let Some(capture) = output_title.captures(trimmed) {
+            captured = Some((
+                capture["binary_id"].to_string(),
+                capture["name"].to_string(),
+                &capture["channel"] == "STDOUT",
+            ));
+            continue;
+        }
+
+        if let Some(capture) = output_header.captures(trimmed) {
+            captured = finished
+                .clone()
+                .map(|(binary_id, name)| (binary_id, name, &capture["channel"] == "stdout"));
+            continue;
+        }
+
+        // nextest right aligns its statuses to 12 columns, a line in the output of a test that looks like a status is indented differently
+        if let Some(capture) = status_line.captures(line)
+            && let Some((attempt, status)) = nextest_status(&capture["status"])
+            && (captured.is_none() || {
+                let status = capture.name("status").unwrap();
+                status.end() == 12 || status.start() == 0
+            })
+        {
+            captured = None;
+            finished = None;
+
+            let binary_id = &capture["binary_id"];
+            let name = &capture["name"];
+
+            let group_index = match parsed_groups.iter().position(|x| x.crate_name == binary_id) {
+                Some(res) => res,
+                None => {
+                    let (target, file_path) = binary_target(binary_id, build);
+                    parsed_groups.push(ParsedTestGroup {
+                        // the binary id is used until the group is finished so the group can be found again
+                        crate_name: binary_id.to_string(),
+                        target: Some(target),
+                        file_path,
+                        tests: Vec::new(),
+                        summary: Some(Summary::default()),
+                        output: None,
+                        warnings: Vec::new(),
+                        crashed: None,
+                    });
+                    parsed_groups.len() - 1
+                }
+            };
+            let group = &mut parsed_groups[group_index];
+
+            let existing = group.tests.iter().position(|x| x.module_path == name);
+            let time = capture["time"].trim_end_matches('s').parse::<f64>().unwrap_or(0.0);
+
+            let status = match status {
+                Some(res) => res,
+                None => {
+                    if capture["status"].ends_with("SLOW") {
+                        let seconds = slow_time
+                            .captures(&capture["time"])
+                            .and_then(|x| x["seconds"].parse::<f64>().ok())
+                            .map(|x| x as u64);
+
+                        match existing {
+                            Some(index) => group.tests[index].slow = seconds,
+                            None => group.tests.push(ParsedTest {
+                                test_type: GeneralTestType::Normal,
+                                module_path: name.to_string(),
+                                // replaced once the test finishes
+                                status: Status::Listed,
+                                file_path: None,
+                                line_number: None,
+                                note: None,
+                                doc_kind: None,
+                                error_reason: None,
+                                failure_kind: None,
+                                backtrace: Vec::new(),
+                                ignore_reason: None,
+                                output: None,
+                                bench: None,
+                                slow: seconds,
+                                retries: 0,
+                                raw_output: None,
+                            }),
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            // a try that will be retried, its output is skipped
+            if capture
+                .name("counter")
+                .is_some_and(|x| x.as_str().contains('─'))
+            {
+                continue;
+            }
+
+            if let Some(summary) = group.summary.as_mut() {
+                *summary += status.clone();
+                summary.time += time;
+                if status == Status::Failed || status == Status::TimedOut {
+                    summary.status = Status::Failed;
+                }
+            }
+
+            let error_reason = if status == Status::TimedOut {
+                Some(format!("timed out after {} seconds", time as u64))
+            } else {
+                None
+            };
+            let retries = attempt.map_or(0, |x| x.saturating_sub(1));
+
+            match existing {
+                Some(index) => {
+                    group.tests[index].status = status;
+                    group.tests[index].error_reason = error_reason;
+                    group.tests[index].retries = retries;
+                }
+                None => group.tests.push(ParsedTest {
+                    test_type: GeneralTestType::Normal,
+                    module_path: name.to_string(),
+                    status,
+                    file_path: None,
+                    line_number: None,
+                    note: None,
+                    doc_kind: None,
+                    error_reason,
+                    failure_kind: None,
+                    backtrace: Vec::new(),
+                    ignore_reason: None,
+                    output: None,
+                    bench: None,
+                    slow: None,
+                    retries,
+                    raw_output: None,
+                }),
+            }
+            finished = Some((binary_id.to_string(), name.to_string()));
+            continue;
+        }
+
+        if separator.is_match(trimmed) {
+            captured = None;
+            continue;
+        }
+
+        if let Some((binary_id, name, stdout)) = &captured {
+            let test = parsed_groups
+                .iter_mut()
+                .filter(|x| &x.crate_name == binary_id)
+                .flat_map(|x| x.tests.iter_mut())
+                .find(|x| &x.module_path == name);
+
+            if let Some(test) = test {
+                // the error reason of a test that timed out is how long it ran for
+                let buffer = if *stdout || test.status != Status::Failed {
+                    &mut test.output
+                } else {
+                    &mut test.error_reason
+                };
+                // newer versions of nextest indent the output under its header
+                let line = line.strip_prefix("    ").unwrap_or(line);
+
+                *buffer = Some(buffer.take().unwrap_or_default() + line + "\n");
+            }
+        }
+    }
+
+    for group in parsed_groups.iter_mut() {
+        group.crate_name = group
+            .target
+            .as_ref()
+            .map_or(group.crate_name.clone(), |x| x.name.replace("-", "_"));
+
+        // tests that were only reported as slow never finished, e.g. the run was cancelled
+        group.tests.retain(|x| x.status != Status::Listed);
+
+        for test in group.tests.iter_mut() {
+            test.output = tidy_output(test.output.take());
+            if test.status == Status::Failed {
+                test.error_reason = tidy_output(test.error_reason.take());
+            }
+        }
+    }
+
+    // nextest only prints a summary for the whole run, and skipped tests aren't counted as run
+    let run: Vec<ParsedTest> = parsed_groups
+        .iter()
+        .flat_map(|x| x.tests.iter())
+        .filter(|x| x.status != Status::Ignored)
+        .cloned()
+        .collect();
+    let mut mismatches = check_counts("all test binaries", &expected, &run);
+    if let Some(group) = parsed_groups.last_mut() {
+        group.warnings.append(&mut mismatches);
+    }
+
+    classify_failures(&mut parsed_groups);
+    parse_backtraces(&mut parsed_groups);
+
+    Ok(TestOutcome::Tests(parsed_groups))
+}
@@ -1,3 +1,4 @@
+use crate::build::{BuildInfo, TargetKind, TestTarget};
 use crate::config::Config;
 use crate::display::Colourise;
 use crate::logger::info;
@@ -6,9 +7,14 @@ use regex::Regex;
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::AddAssign;
 
+/// Returns a [ParseError::InvalidLine] for a line of the given [LineKind], the line number is filled in later by [ParseError::locate].
 macro_rules! parse_error {
-    ($($args:tt)*) => {
-        Err(ParseError { error: format!("ParseError: {}", format!($($args)*)) })
+    ($kind:ident, $line:expr) => {
+        Err(ParseError::InvalidLine {
+            kind: LineKind::$kind,
+            line: $line.to_string(),
+            line_number: None,
+        })
     };
 }
 
@@ -17,14 +23,23 @@ pub enum Status {
     Passed,
     Failed,
     Ignored,
+    /// The test was found by `cargo test -- --list` but was not run
+    Listed,
+    /// The test is a benchmark that was run by `cargo bench`, the results are in [ParsedTest::bench]
+    Measured,
+    /// The test ran for longer than the hard timeout so the test binary was killed, see [crate::config::Config::hard_timeout]
+    TimedOut,
 }
 
 impl Status {
-    fn colour(&self) -> String {
+    pub(crate) fn colour(&self) -> String {
         match self {
             Status::Passed => "Passed".green(),
             Status::Failed => "FAILED".red(),
             Status::Ignored => "Ignored".yellow(),
+            Status::Listed => "Listed".blue(),
+            Status::Measured => "Measured".blue(),
+            Status::TimedOut => "Timed Out".red(),
         }
     }
 }
@@ -35,6 +50,9 @@ impl Display for Status {
             Status::Passed => "Ok".to_string(),
             Status::Failed => "FAILED".to_string(),
             Status::Ignored => "Ignored".to_string(),
+            Status::Listed => "Listed".to_string(),
+            Status::Measured => "Measured".to_string(),
+            Status::TimedOut => "Timed Out".to_string(),
         };
 
         write!(f, "{}", value)
@@ -81,12 +99,159 @@ impl Display for GeneralTestType {
     }
 }
 
+/// How a Doc-test is run, taken from the attributes on its code block, e.g. ```` ```compile_fail ````.
+#[derive(Clone, PartialEq, Debug)]
+pub enum DocTestKind {
+    /// The example is compiled and run.
+    Run,
+    /// The example is compiled but not run, from `no_run`.
+    NoRun,
+    /// The example has to fail to compile, from `compile_fail`.
+    CompileFail,
+    /// The example has to panic when it is run, from `should_panic`.
+    ShouldPanic,
+    /// The example isn't compiled or run, from `ignore`.
+    Ignore,
+}
+
+/// The names of the Doc-test kinds, used by --doc-kind, in the same order as [DocTestKind].
+pub const DOC_TEST_KINDS: [&str; 5] = ["run", "no_run", "compile_fail", "should_panic", "ignore"];
+
+impl DocTestKind {
+    /// Gets the kind from its name, e.g. `compile_fail`.
+    pub fn from_name(name: &str) -> Option<DocTestKind> {
+        match name {
+            "run" => Some(DocTestKind::Run),
+            "no_run" => Some(DocTestKind::NoRun),
+            "compile_fail" => Some(DocTestKind::CompileFail),
+            "should_panic" => Some(DocTestKind::ShouldPanic),
+            "ignore" => Some(DocTestKind::Ignore),
+            _ => None,
+        }
+    }
+
+    /// Gets the kind from the note rustdoc adds after the name of the test, e.g. `compile fail` in `src/lib.rs - add (line 3) - compile fail ... ok`.
+    /// Ignored Doc-tests don't have a note so the status is used for them.
+    fn from_note(note: Option<&str>, status: &Status) -> DocTestKind {
+        match note {
+            Some("compile fail") => DocTestKind::CompileFail,
+            Some("compile") => DocTestKind::NoRun,
+            Some("should panic") => DocTestKind::ShouldPanic,
+            _ if *status == Status::Ignored => DocTestKind::Ignore,
+            _ => DocTestKind::Run,
+        }
+    }
+}
+
+impl Display for DocTestKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DocTestKind::Run => "run",
+                DocTestKind::NoRun => "no_run",
+                DocTestKind::CompileFail => "compile_fail",
+                DocTestKind::ShouldPanic => "should_panic",
+                DocTestKind::Ignore => "ignore",
+            }
+        )
+    }
+}
+
+/// Why a test failed, worked out from its failure reason and notes, see [classify_failures].
+#[derive(Clone, PartialEq, Debug)]
+pub enum FailureKind {
+    /// The test panicked, e.g. from `panic!` or `unwrap()` on `None`.
+    Panic,
+    /// An `assert!`, `assert_eq!` or `assert_ne!` failed.
+    Assertion,
+    /// A `should_panic` test didn't panic.
+    DidNotPanic,
+    /// A `should_panic` test panicked but the message didn't contain the expected string.
+    WrongPanicMessage,
+    /// A test returning a `Result` returned an `Err`.
+    ReturnedErr,
+    /// A `compile_fail` Doc-test compiled.
+    CompileFailCompiled,
+    /// The test process was killed by a signal, e.g. from an abort or a stack overflow.
+    Crash,
+    /// The failure reason didn't match any of the other kinds.
+    Other,
+}
+
+impl FailureKind {
+    /// Works out the kind of a failed test from its failure reason and note.
+    fn classify(test: &ParsedTest) -> FailureKind {
+        let reason = test.error_reason.as_deref().unwrap_or_default();
+        let starts_line = |prefix: &str| reason.lines().any(|x| x.trim().starts_with(prefix));
+
+        if reason.contains("but it's marked `compile_fail`")
+            || test.doc_kind == Some(DocTestKind::CompileFail)
+        {
+            FailureKind::CompileFailCompiled
+        } else if reason.contains("did not panic as expected") {
+            FailureKind::DidNotPanic
+        } else if reason.contains("panic did not contain expected string") {
+            FailureKind::WrongPanicMessage
+        } else if reason.contains("(signal:") || reason.contains("the test binary crashed") {
+            FailureKind::Crash
+        } else if starts_line("assertion `left") || starts_line("assertion failed") {
+            FailureKind::Assertion
+        } else if starts_line("Error: ") {
+            FailureKind::ReturnedErr
+        } else if reason.contains("panicked at") {
+            FailureKind::Panic
+        } else if reason.trim().is_empty() && test.note.as_deref() == Some("should panic") {
+            FailureKind::DidNotPanic
+        } else {
+            FailureKind::Other
+        }
+    }
+
+    /// The name used in the JSON display, e.g. `did_not_panic`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FailureKind::Panic => "panic",
+            FailureKind::Assertion => "assertion",
+            FailureKind::DidNotPanic => "did_not_panic",
+            FailureKind::WrongPanicMessage => "wrong_panic_message",
+            FailureKind::ReturnedErr => "returned_err",
+            FailureKind::CompileFailCompiled => "compile_fail_compiled",
+            FailureKind::Crash => "crash",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+impl Display for FailureKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FailureKind::Panic => "panicked",
+                FailureKind::Assertion => "assertion failed",
+                FailureKind::DidNotPanic => "did not panic",
+                FailureKind::WrongPanicMessage => "wrong panic message",
+                FailureKind::ReturnedErr => "returned an error",
+                FailureKind::CompileFailCompiled => "compiled but should not have",
+                FailureKind::Crash => "crashed",
+                FailureKind::Other => "other",
+            }
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct RawTestGroup {
     test_type: TestType,
     file_path: Vec<String>,
     crate_name: String,
+    target: Option<TestTarget>,
     test_data: Vec<String>,
+    /// Lines that couldn't be parsed in lenient mode, see [Config::lenient].
+    warnings: Vec<ParseError>,
 }
 
 impl RawTestGroup {
@@ -94,36 +259,66 @@ impl RawTestGroup {
         stderr_line: String,
         test_data: Vec<String>,
         is_doc_test: bool,
+        build: Option<&BuildInfo>,
     ) -> Result<RawTestGroup, ParseError> {
         // the path is the crate name found in target/debug/deps/crate_name-xxxxxxxxxxxxxxxx
         // plus the file path found in Running unittests file/path.rs
         // if the test type is Testing then the crate name is the previous unittests crate name
 
+        let executable = Regex::new(r"\((?<executable>[^()]+)\)$").unwrap();
+        let doc_test_beginning = Regex::new(r"Doc-tests (?<crate>[\w-]+)").unwrap();
+
         if is_doc_test {
+            // the stderr line is "Doc-tests crate_name"
+            let crate_name = match doc_test_beginning.captures(stderr_line.as_str()) {
+                Some(res) => res["crate"].to_string(),
+                None => "Doc-tests".to_string(),
+            };
+
             Ok(RawTestGroup {
                 test_type: TestType::Doc,
                 file_path: Vec::new(),
-                crate_name: "Doc-tests".to_string(),
+                target: build.and_then(|x| x.doc_target(&crate_name)),
+                crate_name,
+                test_data,
+                warnings: Vec::new(),
+            })
+        } else if let Some(artifact) = build.and_then(|x| {
+            x.artifact(&executable.captures(stderr_line.trim())?["executable"])
+        }) {
+            // when the artifacts from the build are available the test binary can be looked up directly
+            // rather than relying on the layout of the target directory
+            Ok(RawTestGroup {
+                test_type: match artifact.kind {
+                    TargetKind::Lib | TargetKind::Bin => TestType::Unit,
+                    _ => TestType::Tests,
+                },
+                file_path: artifact
+                    .src_path
+                    .split("/")
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>(),
+                crate_name: artifact.target_name.replace("-", "_"),
+                target: Some(artifact.target()),
                 test_data,
+                warnings: Vec::new(),
             })
         } else {
-            let stderr_message = Regex::new(r"Running (unittests )?(?<path>[\w/\\.-]+) \(target(\\|/)debug(\\|/)deps(\\|/)(?<crate_name>[\w/.-]+)-(?<hash>\w+)(.exe)?\)").unwrap();
+            // the binary is in the deps directory of the profile, e.g. target/debug/deps, target/release/deps for cargo bench or target/<triple>/debug/deps with --target
+            let stderr_message = Regex::new(r"Running (unittests )?(?<path>[\w/\\.-]+) \(.+?(\\|/)deps(\\|/)(?<crate_name>[\w.-]+)-(?<hash>\w+)(.exe)?\)").unwrap();
 
             let capture = match stderr_message.captures(stderr_line.as_str()) {
                 Some(res) => res,
                 None => {
-                    return parse_error!(
-                        "Could not extract data from running line, got \"{}\"",
-                        stderr_line
-                    );
+                    return parse_error!(Running, stderr_line);
                 }
             };
 
             let path = &capture["path"];
             let crate_name = &capture["crate_name"];
 
-            if path.len() == 0 || crate_name.len() == 0 {
-                return parse_error!("No data found in running line.");
+            if path.is_empty() || crate_name.is_empty() {
+                return parse_error!(Running, stderr_line);
             }
 
             let test_type: TestType;
@@ -141,11 +336,37 @@ impl RawTestGroup {
                     .map(|x| x.to_string())
                     .collect::<Vec<String>>(),
                 crate_name: crate_name.to_string(),
+                target: None,
                 test_data,
+                warnings: Vec::new(),
             })
         }
     }
 
+    /// Used in lenient mode for a `Running ...` line that the test binary couldn't be found in, the tests are still parsed but the group is named after the line.
+    fn unknown(stderr_line: String, test_data: Vec<String>, error: ParseError) -> RawTestGroup {
+        RawTestGroup {
+            test_type: TestType::Tests,
+            file_path: vec![stderr_line.trim_start_matches("Running ").to_string()],
+            crate_name: "unknown".to_string(),
+            target: None,
+            test_data,
+            warnings: vec![error],
+        }
+    }
+
+    /// Used for the output of a test binary that was run directly, which doesn't have a `Running ...` line to name the group after.
+    fn unnamed(test_data: Vec<String>) -> RawTestGroup {
+        RawTestGroup {
+            test_type: TestType::Tests,
+            file_path: vec!["test binary".to_string()],
+            crate_name: "unnamed".to_string(),
+            target: None,
+            test_data,
+            warnings: Vec::new(),
+        }
+    }
+
     fn joined_components(&self) -> String {
         self.file_path.join("/")
     }
@@ -169,49 +390,101 @@ pub struct ParsedTest {
     pub status: Status,
     /// Only doc tests have a file path assigned to them.
     pub file_path: Option<String>,
+    /// The line the Doc-test starts on, only doc tests have a line number assigned to them.
+    pub line_number: Option<u32>,
     /// Contains the notes generated by cargo test such as 'shouldn't compile'.
     pub note: Option<String>,
+    /// How the Doc-test is run, taken from the note. Only set for Doc-tests, and only for ignored Doc-tests when the tests were listed rather than run.
+    pub doc_kind: Option<DocTestKind>,
     /// Contains the error reason for failed tests.
     pub error_reason: Option<String>,
+    /// Why the test failed, only set for failed tests, see [classify_failures].
+    pub failure_kind: Option<FailureKind>,
+    /// The frames of the backtrace in the failure reason, only set if the test was run with `RUST_BACKTRACE` set, see [parse_backtraces].
+    pub backtrace: Vec<Frame>,
     /// Contains a possible ignore reason for ignored tests.
     pub ignore_reason: Option<String>,
+    /// Contains the output printed by the test while it was running, e.g. when running with --nocapture.
+    pub output: Option<String>,
+    /// Contains the results of benchmarks, only set when the status is [Status::Measured].
+    pub bench: Option<BenchResult>,
+    /// The number of seconds the test had been running for when it was reported as slow, either by libtest after 60 seconds or by ptest's --timeout.
+    pub slow: Option<u64>,
+    /// The number of times the test was rerun because it failed, see [crate::config::Config::retries]. A passed test with retries is flaky.
+    pub retries: u32,
+    /// The raw bytes of the failure reason or output, whichever contained invalid UTF-8, only set if the test printed invalid UTF-8.
+    /// The invalid bytes are replaced with U+FFFD in [ParsedTest::error_reason] and [ParsedTest::output], see [attach_raw_output].
+    pub raw_output: Option<Vec<u8>>,
+}
+
+/// The result of a benchmark from a line like `test bench_sum ... bench:       1,234 ns/iter (+/- 56)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchResult {
+    pub ns_per_iter: f64,
+    /// The deviation shown after the +/-.
+    pub deviation: f64,
+    /// The throughput, only shown by libtest if the benchmark sets `bytes` on the Bencher.
+    pub mb_per_sec: Option<f64>,
+    /// The ns/iter of the same benchmark in the baseline it was compared against, see [crate::bench].
+    pub baseline: Option<f64>,
+}
+
+impl BenchResult {
+    /// The change from the baseline as a percentage, e.g. -10.0 if the benchmark is 10% faster.
+    pub fn change(&self) -> Option<f64> {
+        let baseline = self.baseline?;
+
+        if baseline == 0.0 {
+            return None;
+        }
+
+        Some((self.ns_per_iter - baseline) / baseline * 100.0)
+    }
+}
+
+/// A frame of a backtrace, e.g. `4: cargo_ptest::tests::panic` followed by `at ./src/tests.rs:8:5`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub index: u32,
+    pub function: String,
+    /// The location of the frame, not all frames have one, e.g. frames from the C runtime.
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    /// Whether the frame is from one of the crates that was tested rather than from std or a dependency.
+    pub in_workspace: bool,
 }
 
 impl ParsedTest {
     fn new(test_line: String) -> Result<ParsedTest, ParseError> {
-        // write regex to validate test line, path, status, note, etc
+        // the names are found by anchoring on the " ... " before the status rather than the characters they contain
+        // as they can contain generics, raw identifiers and unicode, e.g. `src/lib.rs - Wrapper<T, U>::get (line 4)` or `tests::r#type`
         let test_line_match = Regex::new(
-            r"test (?<module_path>[\w:_]+)( - (?<note>[\w\s]+))? ... (?<status>FAILED|ignored|ok)(, (?<ignore_reason>[\w\s]+))?",
+            r"^test (?<module_path>.+?)( - (?<note>.+?))? \.\.\. (?<status>FAILED|ignored|ok)(, (?<ignore_reason>.*))?",
         ).unwrap();
-        let doc_test_line = Regex::new(r"test (?<file_path>[\w/\\.]+) -( (?<module_path>[\w/:]+))? \(line (?<line_num>\d+)\)( - (?<note>[\w\s]+))? \.\.\. (?<status>\w+)").unwrap();
+        let doc_test_line = Regex::new(r"^test (?<file_path>.+?) - ((?<module_path>.+?) )?\(line (?<line_num>\d+)\)( - (?<note>.+?))? \.\.\. (?<status>\w+)(, (?<ignore_reason>.*))?").unwrap();
 
-        if test_line_match.is_match(test_line.as_str()) {
+        // a Doc-test line also matches the normal test line so it has to be checked for first
+        if !doc_test_line.is_match(test_line.as_str())
+            && test_line_match.is_match(test_line.as_str())
+        {
             let capture = match test_line_match.captures(test_line.as_str()) {
                 Some(res) => res,
                 None => {
-                    return parse_error!(
-                        "Data could not be extracted from provided test line, got \"{}\"",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
 
             let path = match capture.name("module_path") {
                 Some(res) => res.as_str().to_string(),
                 None => {
-                    return parse_error!(
-                        "Could not extract module path from test line, test line is {}",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
             let status_string = match capture.name("status") {
                 Some(res) => res.as_str(),
                 None => {
-                    return parse_error!(
-                        "Could not extract status from test line, test line is {}",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
 
@@ -222,22 +495,24 @@ impl ParsedTest {
                 module_path: path,
                 status,
                 file_path: None,
-                note: capture
-                    .name("note")
-                    .map_or(None, |x| Some(x.as_str().to_string())),
+                line_number: None,
+                note: capture.name("note").map(|x| unescape(x.as_str())),
+                doc_kind: None,
                 error_reason: None,
-                ignore_reason: capture
-                    .name("ignore_reason")
-                    .map_or(None, |x| Some(x.as_str().to_string())),
+                failure_kind: None,
+                backtrace: Vec::new(),
+                output: None,
+                bench: None,
+                slow: None,
+                retries: 0,
+                raw_output: None,
+                ignore_reason: capture.name("ignore_reason").map(|x| unescape(x.as_str())),
             })
         } else if doc_test_line.is_match(test_line.as_str()) {
             let capture = match doc_test_line.captures(test_line.as_str()) {
                 Some(res) => res,
                 None => {
-                    return parse_error!(
-                        "Data could not be extracted from provided Doc-test line, got \"{}\"",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
 
@@ -249,41 +524,175 @@ impl ParsedTest {
             let file_path = match capture.name("file_path") {
                 Some(res) => res.as_str().to_string(),
                 None => {
-                    return parse_error!(
-                        "Could not extract file path from Doc-test line, test line is \"{}\"",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
 
             let status_string = match capture.name("status") {
                 Some(res) => res.as_str(),
                 None => {
-                    return parse_error!(
-                        "Could not extract status from test line, test line is \"{}\"",
-                        test_line
-                    );
+                    return parse_error!(Test, test_line);
                 }
             };
 
             let status = ParsedTest::match_status(status_string);
+            let note = capture.name("note").map(|x| unescape(x.as_str()));
 
             Ok(ParsedTest {
                 test_type: GeneralTestType::Doc,
                 module_path,
-                status,
                 file_path: Some(file_path),
-                note: capture
-                    .name("note")
-                    .map_or(None, |x| Some(x.as_str().to_string())),
+                line_number: capture
+                    .name("line_num")
+                    .and_then(|x| x.as_str().parse::<u32>().ok()),
+                doc_kind: Some(DocTestKind::from_note(note.as_deref(), &status)),
+                note,
+                status,
+                error_reason: None,
+                failure_kind: None,
+                backtrace: Vec::new(),
+                output: None,
+                bench: None,
+                slow: None,
+                retries: 0,
+                raw_output: None,
+                ignore_reason: capture.name("ignore_reason").map(|x| unescape(x.as_str())),
+            })
+        } else {
+            parse_error!(Test, test_line)
+        }
+    }
+
+    /// Parses a benchmark line, e.g. `test benches::sum   ... bench:           0.84 ns/iter (+/- 0.30) = 11377 MB/s`.
+    /// Older versions of libtest print the values as integers with commas, e.g. `1,234 ns/iter (+/- 56)`.
+    fn from_bench_line(bench_line: String) -> Result<ParsedTest, ParseError> {
+        let bench_line_match = Regex::new(r"^test (?<module_path>.+?)\s+\.\.\. bench:\s+(?<ns_per_iter>[\d,.]+) ns/iter \(\+/- (?<deviation>[\d,.]+)\)( = (?<mb_per_sec>[\d,.]+) MB/s)?$").unwrap();
+
+        let capture = match bench_line_match.captures(bench_line.as_str()) {
+            Some(res) => res,
+            None => {
+                return parse_error!(Bench, bench_line);
+            }
+        };
+
+        let number = |value: &str| value.replace(",", "").parse::<f64>().ok();
+
+        let (ns_per_iter, deviation) =
+            match (number(&capture["ns_per_iter"]), number(&capture["deviation"])) {
+                (Some(ns_per_iter), Some(deviation)) => (ns_per_iter, deviation),
+                _ => {
+                    return parse_error!(Bench, bench_line);
+                }
+            };
+
+        Ok(ParsedTest {
+            test_type: GeneralTestType::Normal,
+            module_path: capture["module_path"].to_string(),
+            status: Status::Measured,
+            file_path: None,
+            line_number: None,
+            note: None,
+            doc_kind: None,
+            error_reason: None,
+            failure_kind: None,
+            backtrace: Vec::new(),
+            output: None,
+            ignore_reason: None,
+            slow: None,
+            retries: 0,
+            raw_output: None,
+            bench: Some(BenchResult {
+                ns_per_iter,
+                deviation,
+                mb_per_sec: capture.name("mb_per_sec").and_then(|x| number(x.as_str())),
+                baseline: None,
+            }),
+        })
+    }
+
+    /// Parses a line from the output of `cargo test -- --list`, e.g. `tests::panic: test`.
+    /// Set ignored to true if the line came from `cargo test -- --list --ignored`.
+    fn from_list_line(list_line: String, ignored: bool) -> Result<ParsedTest, ParseError> {
+        let list_line_match = Regex::new(r"^(?<module_path>.+): (?<kind>test|bench)$").unwrap();
+        let doc_list_line_match = Regex::new(r"^(?<file_path>.+?) - ((?<module_path>.+?) )?\(line (?<line_num>\d+)\): (?<kind>test|bench)$").unwrap();
+
+        let status = if ignored {
+            Status::Ignored
+        } else {
+            Status::Listed
+        };
+
+        // a listed Doc-test also matches the normal list line so it has to be checked for first
+        if !doc_list_line_match.is_match(list_line.as_str())
+            && let Some(capture) = list_line_match.captures(list_line.as_str())
+        {
+            Ok(ParsedTest {
+                test_type: GeneralTestType::Normal,
+                module_path: capture["module_path"].to_string(),
+                status,
+                file_path: None,
+                line_number: None,
+                note: if &capture["kind"] == "bench" {
+                    Some("bench".to_string())
+                } else {
+                    None
+                },
+                doc_kind: None,
+                error_reason: None,
+                failure_kind: None,
+                backtrace: Vec::new(),
+                output: None,
+                bench: None,
+                slow: None,
+                retries: 0,
+                raw_output: None,
+                ignore_reason: None,
+            })
+        } else if let Some(capture) = doc_list_line_match.captures(list_line.as_str()) {
+            Ok(ParsedTest {
+                test_type: GeneralTestType::Doc,
+                module_path: capture
+                    .name("module_path")
+                    .map_or(String::new(), |x| x.as_str().to_string()),
+                status,
+                file_path: Some(capture["file_path"].to_string()),
+                line_number: capture["line_num"].parse::<u32>().ok(),
+                note: None,
+                // --list doesn't show the notes so only ignored Doc-tests are known
+                doc_kind: if ignored {
+                    Some(DocTestKind::Ignore)
+                } else {
+                    None
+                },
                 error_reason: None,
+                failure_kind: None,
+                backtrace: Vec::new(),
+                output: None,
+                bench: None,
+                slow: None,
+                retries: 0,
+                raw_output: None,
                 ignore_reason: None,
             })
         } else {
-            parse_error!(
-                "Provided string wasn't normal test line or a Doc-test line, got {}",
-                test_line
-            )
+            parse_error!(List, list_line)
+        }
+    }
+
+    /// Gets the name libtest uses for the test, e.g. `tests::panic` or `src/lib.rs - attribute::ignore (line 3)` for a Doc-test.
+    pub fn name(&self) -> String {
+        match self.test_type {
+            GeneralTestType::Doc => format!(
+                "{} - {}(line {})",
+                self.file_path.clone().unwrap_or_default(),
+                if self.module_path.is_empty() {
+                    "".to_string()
+                } else {
+                    format!("{} ", self.module_path)
+                },
+                self.line_number.map_or("?".to_string(), |x| x.to_string())
+            ),
+            GeneralTestType::Normal => self.module_path.clone(),
         }
     }
 
@@ -291,6 +700,13 @@ impl ParsedTest {
         self.error_reason = Some(error_reason)
     }
 
+    fn add_output(&mut self, output: String) {
+        self.output = Some(match self.output.take() {
+            Some(res) => res + output.as_str(),
+            None => output,
+        })
+    }
+
     fn match_status(status_string: &str) -> Status {
         match &status_string {
             x if x.contains("ok") => Status::Passed,
@@ -305,14 +721,23 @@ impl Display for ParsedTest {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "\nParsedTest {{\n    test_type: {}\n    module_path: {}\n    status: {}\n    file_path: {:?}\n    note: {:?}\n    error_reason: {:?}\n    ignore_reason: {:?}\n}}",
+            "\nParsedTest {{\n    test_type: {}\n    module_path: {}\n    status: {}\n    file_path: {:?}\n    line_number: {:?}\n    note: {:?}\n    doc_kind: {:?}\n    error_reason: {:?}\n    failure_kind: {:?}\n    backtrace: {:?}\n    ignore_reason: {:?}\n    output: {:?}\n    bench: {:?}\n    slow: {:?}\n    retries: {}\n    raw_output: {:?}\n}}",
             self.test_type,
             self.module_path,
             self.status,
             self.file_path,
+            self.line_number,
             self.note,
+            self.doc_kind,
             self.error_reason,
-            self.ignore_reason
+            self.failure_kind,
+            self.backtrace,
+            self.ignore_reason,
+            self.output,
+            self.bench,
+            self.slow,
+            self.retries,
+            self.raw_output
         )
     }
 }
@@ -336,24 +761,36 @@ pub struct Summary {
 }
 
 impl Summary {
+    /// Recounts the number of passed, failed, ignored and measured tests and the status from the tests, the number filtered and the time are kept.
+    pub(crate) fn recount(&mut self, tests: &[ParsedTest]) {
+        self.passed = 0;
+        self.failed = 0;
+        self.ignored = 0;
+        self.measured = 0;
+
+        for test in tests {
+            *self += test.status.clone();
+        }
+
+        self.status = if self.failed > 0 {
+            Status::Failed
+        } else {
+            Status::Passed
+        };
+    }
+
     /// If the given summary line does not match the regex then the function will return an error, if any of the numeric values cannot be extracted then they are replaced by 0.
     pub fn new(summary_line: &str) -> Result<Summary, ParseError> {
         let tests_summary_line = Regex::new(r"test result: (?<overall_result>\w+)\. (?<passed>\d+) passed; (?<failed>\d+) failed; (?<ignored>\d+) ignored; (?<measured>\d+) measured; (?<filtered_out>\d+) filtered out; finished in (?<finish_time>[\d.]+)s").unwrap();
 
         if !tests_summary_line.is_match(summary_line) {
-            return parse_error!(
-                "Data could not be extracted from provided summary line, got \"{}\"",
-                summary_line
-            );
+            return parse_error!(Summary, summary_line);
         }
 
         let capture = match tests_summary_line.captures(summary_line) {
             Some(res) => res,
             None => {
-                return parse_error!(
-                    "Data could not be extracted from provided summary line, got \"{}\"",
-                    summary_line
-                );
+                return parse_error!(Summary, summary_line);
             }
         };
 
@@ -363,34 +800,31 @@ impl Summary {
                 "ok" => Status::Passed,
                 "FAILED" => Status::Failed,
                 _ => {
-                    return parse_error!(
-                        "Status extracted from summary line could not be recognised, got {}",
-                        &capture["overall_result"]
-                    );
+                    return parse_error!(Summary, summary_line);
                 }
             },
-            passed: match &capture["passed"].parse::<u32>() {
-                Ok(res) => res.clone(),
+            passed: match capture["passed"].parse::<u32>() {
+                Ok(res) => res,
                 Err(_) => 0,
             },
-            failed: match &capture["failed"].parse::<u32>() {
-                Ok(res) => res.clone(),
+            failed: match capture["failed"].parse::<u32>() {
+                Ok(res) => res,
                 Err(_) => 0,
             },
-            ignored: match &capture["ignored"].parse::<u32>() {
-                Ok(res) => res.clone(),
+            ignored: match capture["ignored"].parse::<u32>() {
+                Ok(res) => res,
                 Err(_) => 0,
             },
-            measured: match &capture["measured"].parse::<u32>() {
-                Ok(res) => res.clone(),
+            measured: match capture["measured"].parse::<u32>() {
+                Ok(res) => res,
                 Err(_) => 0,
             },
-            filtered: match &capture["filtered_out"].parse::<u32>() {
-                Ok(res) => res.clone(),
+            filtered: match capture["filtered_out"].parse::<u32>() {
+                Ok(res) => res,
                 Err(_) => 0,
             },
-            time: match &capture["finish_time"].parse::<f64>() {
-                Ok(res) => res.clone(),
+            time: match capture["finish_time"].parse::<f64>() {
+                Ok(res) => res,
                 Err(_) => 0.0,
             },
         })
@@ -451,21 +885,45 @@ impl AddAssign for Summary {
 impl AddAssign<Status> for Summary {
     fn add_assign(&mut self, rhs: Status) {
         match rhs {
-            Status::Failed => self.failed += 1,
+            Status::Failed | Status::TimedOut => self.failed += 1,
             Status::Ignored => self.ignored += 1,
             Status::Passed => self.passed += 1,
+            Status::Measured => self.measured += 1,
+            Status::Listed => {}
         }
     }
 }
 
 #[derive(Clone, Debug)]
 /// The tests for each file are seperated and stored in this struct. E.g. all the tests in src/main.rs would be in a [ParsedTestGroup] and all the tests from src/lib.rs would be in another [ParsedTestGroup].
-/// All Doc-tests, regardless of which file they are from, are stored in a single [ParsedTestGroup]. They will have an empty file_path and summary and the crate_name will be "Doc-tests".
+/// The Doc-tests for each crate, regardless of which file they are from, are stored in a single [ParsedTestGroup]. They will have an empty file_path and the crate_name will be the one from the `Doc-tests crate_name` line.
+/// If cargo runs the Doc-tests for a crate in several blocks then the summary is the sum of the summary of each block.
 pub struct ParsedTestGroup {
+    /// The name of the crate being tested, taken from the name of the test binary or the `Doc-tests crate_name` line.
     pub crate_name: String,
+    /// The package and target the tests came from, only available when the tests were built by [crate::run::run].
+    pub target: Option<TestTarget>,
     pub file_path: Vec<String>,
     pub tests: Vec<ParsedTest>,
     pub summary: Option<Summary>,
+    /// Output printed while the tests were running that couldn't be attributed to a single test.
+    pub output: Option<String>,
+    /// Lines that couldn't be parsed and were skipped, only used in lenient mode, see [Config::lenient].
+    /// A test line that couldn't be parsed is kept in [ParsedTestGroup::output].
+    /// Counts in the output that don't match the tests that were parsed are also added here, see [check_counts].
+    pub warnings: Vec<ParseError>,
+    /// Set if the test binary crashed before printing its summary, e.g. from an abort, a segfault or being killed for running out of memory.
+    /// The summary is counted from the tests that finished and the test that was running is marked as failed.
+    pub crashed: Option<Crash>,
+}
+
+/// How a test binary crashed, from the `process didn't exit successfully` line cargo prints after it, see [ParsedTestGroup::crashed].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crash {
+    /// The exit status reported by cargo, e.g. `signal: 6, SIGABRT: process abort signal`.
+    pub status: String,
+    /// The number of tests from the `running N tests` line that never reported a result, not counting the test that was running if it could be found.
+    pub missing: u32,
 }
 
 pub trait AggregateSummary {
@@ -477,8 +935,8 @@ impl AggregateSummary for Vec<ParsedTestGroup> {
         let mut summary = Summary::default();
 
         for i in self {
-            if i.summary.is_some() {
-                summary += i.summary.unwrap()
+            if let Some(res) = i.summary {
+                summary += res
             }
         }
 
@@ -490,311 +948,1271 @@ impl Display for ParsedTestGroup {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "ParsedTestGroup {{   crate_name: {},\n   file_path: {:?},\n    tests: {:?},\n    summary: {:?} }}",
+            "ParsedTestGroup {{   crate_name: {},\n   target: {:?},\n   file_path: {:?},\n    tests: {:?},\n    summary: {:?},\n    output: {:?},\n    warnings: {:?},\n    crashed: {:?} }}",
             self.crate_name,
+            self.target,
             self.file_path,
             self.tests,
             match self.summary.clone() {
                 Some(res) => res.to_string(),
                 None => "".to_string(),
-            }
+            },
+            self.output,
+            self.warnings,
+            self.crashed
         )
     }
 }
 
-#[derive(Debug)]
-pub struct ParseError {
-    pub error: String,
+#[derive(Clone, Debug)]
+/// An error from rustc found in stderr when the tests fail to build.
+pub struct CompileError {
+    /// The error code, e.g. E0308, not all errors have a code.
+    pub code: Option<String>,
+    pub message: String,
+    pub file_path: Option<String>,
+    pub line_number: Option<u32>,
+    pub column: Option<u32>,
+    /// The package the error came from, only known when the error was read from cargo's JSON messages.
+    pub package_name: Option<String>,
+}
+
+impl CompileError {
+    /// The location of the error formatted as file:line:column, None if rustc didn't give a location.
+    pub fn location(&self) -> Option<String> {
+        let file_path = self.file_path.clone()?;
+
+        Some(match (self.line_number, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", file_path, line, column),
+            (Some(line), None) => format!("{}:{}", file_path, line),
+            _ => file_path,
+        })
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "error{}: {}{}",
+            self.code
+                .clone()
+                .map_or("".to_string(), |x| format!("[{}]", x)),
+            self.message,
+            self.location()
+                .map_or("".to_string(), |x| format!(" at {}", x))
+        )
+    }
+}
+
+/// The result of parsing the output of cargo test.
+pub enum TestOutcome {
+    /// The tests were built and run (or listed), contains the results for each group of tests.
+    Tests(Vec<ParsedTestGroup>),
+    /// The tests failed to build so none of them were run, contains the errors from rustc.
+    BuildFailed(Vec<CompileError>),
+}
+
+impl TestOutcome {
+    /// Gets the results of the tests, a failed build is returned as [RunError::BuildFailed] so the results can be used with `?`.
+    pub fn into_tests(self) -> Result<Vec<ParsedTestGroup>, RunError> {
+        match self {
+            TestOutcome::Tests(res) => Ok(res),
+            TestOutcome::BuildFailed(errors) => Err(RunError::BuildFailed(errors)),
+        }
+    }
+}
+
+impl From<Vec<ParsedTestGroup>> for TestOutcome {
+    fn from(value: Vec<ParsedTestGroup>) -> Self {
+        TestOutcome::Tests(value)
+    }
+}
+
+/// The kind of line that [ParseError::InvalidLine] couldn't parse.
+#[derive(Clone, PartialEq, Debug)]
+pub enum LineKind {
+    /// A `Running ...` line that didn't contain the path of a test binary, e.g. `Running unittests src/lib.rs (target/debug/deps/crate-hash)`.
+    Running,
+    /// The `running N tests` line at the start of a block of tests.
+    BlockStart,
+    /// A test line, e.g. `test tests::panic ... FAILED`.
+    Test,
+    /// A benchmark line, e.g. `test benches::sum ... bench: 0.84 ns/iter (+/- 0.30)`.
+    Bench,
+    /// A line from the output of `cargo test -- --list`, e.g. `tests::panic: test`.
+    List,
+    /// A summary line, e.g. `test result: ok. 1 passed; 0 failed; ...`.
+    Summary,
+}
+
+impl Display for LineKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LineKind::Running => "Running",
+            LineKind::BlockStart => "running N tests",
+            LineKind::Test => "test",
+            LineKind::Bench => "benchmark",
+            LineKind::List => "listed test",
+            LineKind::Summary => "summary",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// An error from parsing the output of cargo test.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ParseError {
+    /// A line didn't have the format that was expected, the line number is the line in the output passed to the parser, starting from 1.
+    InvalidLine {
+        kind: LineKind,
+        line: String,
+        line_number: Option<usize>,
+    },
+    /// A group of tests ended without a `test result:` line, e.g. because the output was cut off. A test binary that crashed is reported in [ParsedTestGroup::crashed] instead.
+    MissingSummary { group: String },
+    /// The number of tests parsed didn't match a count printed in the output, e.g. a test line was skipped so the summary says there is one more passed test than was parsed.
+    /// This is always reported as a warning rather than an error, see [check_counts].
+    CountMismatch {
+        group: String,
+        /// What was counted, e.g. `passed` for the passed count in the summary or `run` for the `running N tests` line.
+        count: &'static str,
+        expected: u32,
+        found: u32,
+    },
+    /// The output didn't contain any tests, e.g. the wrong file was passed to [crate::run::report].
+    NoTests,
 }
 
 impl ParseError {
     pub fn to_run_error(&self) -> RunError {
-        RunError {
-            error: self.error.clone(),
+        RunError::Parse(self.clone())
+    }
+
+    /// Fills in the line number of a [ParseError::InvalidLine] using the first line of output that matches the offending line.
+    fn locate(self, output: &str) -> ParseError {
+        match self {
+            ParseError::InvalidLine {
+                kind,
+                line,
+                line_number: None,
+            } if !line.trim().is_empty() => ParseError::InvalidLine {
+                // a test line can be made from a test name and a status printed on different lines, so the name is looked for if the whole line isn't found
+                line_number: output
+                    .split('\n')
+                    .position(|x| x.trim() == line.trim())
+                    .or_else(|| {
+                        let name = line.split(" ... ").next()?;
+                        output.split('\n').position(|x| x.trim().starts_with(name))
+                    })
+                    .map(|x| x + 1),
+                kind,
+                line,
+            },
+            res => res,
         }
     }
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidLine {
+                kind,
+                line,
+                line_number: Some(line_number),
+            } => write!(f, "Could not parse the {} line at line {}: \"{}\"", kind, line_number, line),
+            ParseError::InvalidLine { kind, line, .. } => {
+                write!(f, "Could not parse the {} line: \"{}\"", kind, line)
+            }
+            ParseError::MissingSummary { group } => {
+                write!(f, "Could not find the summary line for {}", group)
+            }
+            ParseError::CountMismatch {
+                group,
+                count,
+                expected,
+                found,
+            } => write!(
+                f,
+                "The counts for {} don't match, the output says {} {} but {} were parsed",
+                group, expected, count, found
+            ),
+            ParseError::NoTests => write!(
+                f,
+                "Could not find any tests, expected the output of cargo test or of a test binary"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 fn get_next<'a>(iter: &mut dyn Iterator<Item = &'a str>) -> Option<&'a str> {
     loop {
         let next = iter.next()?;
 
-        if next.len() != 0 {
+        if !next.is_empty() {
             return Some(next);
         }
     }
 }
 
-fn summarise_doctests(parsed_tests: Vec<ParsedTest>) -> Summary {
-    let mut summary = Summary::default();
+/// Unescapes the escapes from a Rust string literal in an ignore reason or note, e.g. `a \"quoted\" reason` becomes `a "quoted" reason`.
+/// A backslash that isn't part of an escape is kept so a reason like `C:\ptest` is unchanged.
+fn unescape(text: &str) -> String {
+    let mut res = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some('\\') => res.push('\\'),
+            Some('"') => res.push('"'),
+            Some('\'') => res.push('\''),
+            Some('n') => res.push('\n'),
+            Some('t') => res.push('\t'),
+            Some('r') => res.push('\r'),
+            _ => {
+                res.push('\\');
+                continue;
+            }
+        }
+        chars.next();
+    }
+
+    res
+}
 
-    for i in parsed_tests {
-        summary += i.status
+/// Creates the group for a `Running ...` or `Doc-tests ...` line, in lenient mode a line that can't be parsed still creates a group, see [RawTestGroup::unknown].
+fn raw_group(
+    header: String,
+    test_data: Vec<String>,
+    build: Option<&BuildInfo>,
+    lenient: bool,
+) -> Result<RawTestGroup, ParseError> {
+    let is_doc_test = header.starts_with("Doc-tests ");
+
+    match RawTestGroup::new(header.clone(), test_data.clone(), is_doc_test, build) {
+        Err(err) if lenient => Ok(RawTestGroup::unknown(header, test_data, err)),
+        res => res,
     }
-    summary
 }
 
+/// Splits the combined output of cargo test into a group for each `Running ...` and `Doc-tests ...` line from stderr.
+/// Each group contains the lines printed after its Running or Doc-tests line, up until the next one, untrimmed so the output of the tests keeps its indentation.
+/// Anything printed before the first Running line, e.g. the compiler output, is skipped.
+/// If there are no Running or Doc-tests lines, e.g. the output of a test binary that was run directly, the lines from the first `running N tests` line are a single unnamed group.
 fn merge_outputs(
-    stdout: String,
-    stderr: String,
+    output: &str,
+    build: Option<&BuildInfo>,
     debug: bool,
+    lenient: bool,
 ) -> Result<Vec<RawTestGroup>, ParseError> {
-    let block_beginning = Regex::new(r"running (\d+) test(s?)").unwrap();
-    let doc_test_beginning = Regex::new(r"Doc-tests (?<crate>[\w-]+)").unwrap();
+    let windows_safe_out = output.replace("\r", ""); // remove any carriage returns windows might be adding
 
-    let windows_safe_out = stdout.replace("\r", ""); // remove any carriage returns windows might be adding
-    let windows_safe_err = stderr.replace("\r", "");
-    if debug {
-        info!("");
-        println!(
-            "{:?}",
-            windows_safe_err.clone().split("\n").collect::<Vec<&str>>()
-        );
-    }
-    let mut err_lines = windows_safe_err.split("\n").filter(|x| {
-        return if x.trim().starts_with("Running ") || x.trim().starts_with("Doc-tests") {
+    let mut blocks: Vec<RawTestGroup> = Vec::new();
+    // the Running or Doc-tests line for the group currently being read
+    let mut header: Option<String> = None;
+    let mut buffer: Vec<String> = Vec::new();
+    let block_beginning = Regex::new(r"^running \d+ tests?$").unwrap();
+
+    for x in windows_safe_out.split("\n") {
+        let line = x.trim();
+
+        if line.starts_with("Running ") || line.starts_with("Doc-tests ") {
             if debug {
-                info!("Adding line \"{}\" to err_lines", x);
+                info!("Starting new group at line: {}", line);
             }
-            true
-        } else {
-            false
-        };
-    });
 
-    let lines = windows_safe_out.split("\n").filter(|x| x.len() != 0);
+            if let Some(res) = header.take() {
+                blocks.push(raw_group(res, buffer, build, lenient)?);
+            }
 
-    let mut blocks: Vec<RawTestGroup> = Vec::new();
-    let mut buffer: Vec<String> = Vec::new();
-    let mut reached_doc_tests = false;
+            header = Some(line.to_string());
+            buffer = Vec::new();
+        } else if (header.is_some() || !buffer.is_empty() || block_beginning.is_match(line))
+            && !line.is_empty()
+        {
+            buffer.push(x.to_string());
+        }
+    }
+
+    match header {
+        Some(res) => blocks.push(raw_group(res, buffer, build, lenient)?),
+        None if !buffer.is_empty() => blocks.push(RawTestGroup::unnamed(buffer)),
+        None => {}
+    }
 
     if debug {
-        info!("Filtered stdout from merge_outputs");
-        println!("{:?}\n\n", lines.clone().collect::<Vec<&str>>());
-        info!("Filtered stderr from merge_outputs");
-        println!("{:?}", err_lines.clone().collect::<Vec<&str>>());
+        info!("\nOutput of merging stderr and stdout");
+        info!("Made {} blocks of tests", blocks.len());
+        for i in blocks.iter() {
+            println!("merge_outputs block: {}", i)
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Splits the lines of a group at each `running N tests` line.
+/// Normal groups only have one block but cargo can run the Doc-tests for a crate in several blocks, e.g. merged doctests, doctests for older editions and compile_fail doctests are each run separately.
+fn split_blocks(test_data: &[String]) -> Vec<&[String]> {
+    let block_beginning = Regex::new(r"^running \d+ tests?$").unwrap();
+
+    let starts: Vec<usize> = test_data
+        .iter()
+        .enumerate()
+        .filter(|x| block_beginning.is_match(x.1.trim()))
+        .map(|x| x.0)
+        .collect();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(test_data.len());
+            &test_data[*start..end]
+        })
+        .collect()
+}
+
+/// Creates the test for a test line that was split by output from the test, e.g. when running with --nocapture.
+/// last_output is any output printed on the same line as the status.
+fn finish_test(
+    test_line: String,
+    mut output: String,
+    last_output: String,
+    status: String,
+) -> Result<ParsedTest, ParseError> {
+    let mut parsed_test = ParsedTest::new(format!("{} ... {}", test_line, status))?;
+
+    if !last_output.is_empty() {
+        output += format!("{}\n", last_output).as_str();
     }
+    if !output.is_empty() {
+        parsed_test.add_output(output);
+    }
+
+    Ok(parsed_test)
+}
 
-    for x in lines {
-        if debug {
-            info!("Checking line: {}", x);
+/// Returns the error, or in lenient mode adds it to the warnings and returns None so the line can be skipped.
+fn recover<T>(
+    result: Result<T, ParseError>,
+    lenient: bool,
+    warnings: &mut Vec<ParseError>,
+) -> Result<Option<T>, ParseError> {
+    match result {
+        Ok(res) => Ok(Some(res)),
+        Err(err) if lenient => {
+            warnings.push(err);
+            Ok(None)
         }
-        if reached_doc_tests {
-            buffer.push(x.trim().to_string());
-            continue;
+        Err(err) => Err(err),
+    }
+}
+
+/// The section of a block of tests that is currently being parsed.
+#[derive(PartialEq)]
+enum BlockSection {
+    /// The test lines and any output printed by the tests while they run.
+    Tests,
+    /// The output of the passed tests under the first "successes:", only printed when running with --show-output.
+    Successes,
+    /// The list of passed tests under the second "successes:".
+    SuccessList,
+    /// The output of the failed tests under the first "failures:".
+    Failures,
+    /// The list of failed tests under the second "failures:".
+    FailureList,
+}
+
+/// Parses a single block of tests, starting at the `running N tests` line and ending at the summary line.
+/// Any lines between the test lines that aren't test lines are treated as output from the tests (e.g. when running with --nocapture).
+/// If a test's name was printed before it finished (e.g. `test tests::slow ... `) then the output is given to that test, otherwise it is returned separately.
+/// The output under "successes:" is given to the passed tests and the output under "failures:" is used as the error reason of the failed tests.
+/// In lenient mode lines that can't be parsed are added to warnings and kept as output rather than returning an error, see [Config::lenient].
+/// If the test binary crashed before printing the summary then crashed is set, see [ParsedTestGroup::crashed].
+fn parse_block(
+    block: &[String],
+    group: &RawTestGroup,
+    lenient: bool,
+    warnings: &mut Vec<ParseError>,
+    crashed: &mut Option<Crash>,
+) -> Result<(Vec<ParsedTest>, Summary, String), ParseError> {
+    let test_block_start_match = Regex::new(r"running (?<count>\d+) test(s?)").unwrap();
+    let test_line_start = Regex::new(r"^(?<test>test .+?) \.\.\.( (?<rest>.*))?$").unwrap();
+    let finished_status = Regex::new(r"^(ok|FAILED|ignored)(, .*)?$").unwrap();
+    let trailing_status = Regex::new(r"^(?<output>.*?)(?<status>ok|FAILED|ignored)$").unwrap();
+    let slow_test = Regex::new(r"^test (?<name>.+) has been running for over (?<seconds>\d+) seconds$").unwrap();
+    let timed_out_test = Regex::new(r"^test (?<name>.+) timed out after (?<seconds>\d+) seconds$").unwrap();
+    let output_title = Regex::new(r"^---- (?<name>.+) (?<channel>stdout|stderr) ----$").unwrap();
+    // printed by cargo on stderr when a test binary fails, before the summary only if the binary crashed
+    let binary_failed = Regex::new(r"^(?<before>.*?)error: \w+ failed, to rerun pass .+$").unwrap();
+    let exit_status =
+        Regex::new(r"^process didn't exit successfully: `.+` \((?<status>.+)\)$").unwrap();
+
+    // the lines are matched trimmed but the output of the tests keeps its indentation, e.g. the frames of a backtrace
+    let mut line_iter = block.iter().map(|x| (x.trim(), x.as_str())).peekable();
+    let mut parsed_tests: Vec<ParsedTest> = Vec::new();
+
+    let test_block_start = get_next(&mut line_iter.by_ref().map(|x| x.0)).unwrap_or("");
+    let test_count = test_block_start_match
+        .captures(test_block_start)
+        .and_then(|x| x["count"].parse::<u32>().ok());
+
+    if test_count.is_none() {
+        recover::<()>(parse_error!(BlockStart, test_block_start), lenient, warnings)?;
+    }
+
+    let mut section = BlockSection::Tests;
+    // a test that has printed its name but not its status, along with the output printed since
+    let mut unfinished_test: Option<(String, String)> = None;
+    let mut unattributed_output = String::new();
+    // the name of the test whose output is being read under "successes:" or "failures:" and the output so far
+    let mut captured: Option<(String, String)> = None;
+    let mut summary: Option<Summary> = None;
+    // the tests that were reported as slow and how long they had been running for
+    let mut slow_tests: Vec<(String, u64)> = Vec::new();
+    // the exit status of the test binary if it crashed before printing the summary
+    let mut crash_status: Option<String> = None;
+
+    // add the output read under "successes:" or "failures:" to the correct test
+    let add_captured = |parsed_tests: &mut Vec<ParsedTest>,
+                        section: &BlockSection,
+                        captured: Option<(String, String)>| {
+        if let Some((name, buffer)) = captured {
+            for i in parsed_tests.iter_mut() {
+                if i.name() == name {
+                    match section {
+                        BlockSection::Successes => i.add_output(buffer.clone()),
+                        _ => i.add_error_reason(buffer.clone()),
+                    }
+                }
+            }
         }
+    };
 
-        if block_beginning.is_match(&x) {
-            let next = match get_next(&mut err_lines) {
-                Some(res) => res,
-                None => continue,
+    while let Some((line, raw_line)) = line_iter.next() {
+        // the output of a test under "successes:" or "failures:" can contain anything, the summary comes after the list of tests
+        let in_output = section == BlockSection::Successes || section == BlockSection::Failures;
+
+        if line.starts_with("test result:") && !in_output {
+            add_captured(&mut parsed_tests, &section, captured.take());
+            summary = recover(Summary::new(line), lenient, warnings)?;
+            break;
+        }
+
+        if let Some(capture) = binary_failed.captures(line) {
+            add_captured(&mut parsed_tests, &section, captured.take());
+
+            // the test that was running when the binary crashed has printed its name but not its status, e.g. "test tests::aborts ... "
+            let before = capture["before"].trim();
+            if let Some(test) = test_line_start.captures(before) {
+                if let Some((_, output)) = unfinished_test.take() {
+                    unattributed_output += output.as_str();
+                }
+                unfinished_test = Some((test["test"].to_string(), String::new()));
+            } else if !before.is_empty() {
+                match unfinished_test.as_mut() {
+                    Some((_, output)) => *output += format!("{}\n", before).as_str(),
+                    None => unattributed_output += format!("{}\n", before).as_str(),
+                }
+            }
+
+            crash_status = Some(
+                line_iter
+                    .find_map(|(x, _)| exit_status.captures(x).map(|x| x["status"].to_string()))
+                    .unwrap_or("unknown exit status".to_string()),
+            );
+            break;
+        }
+
+        match section {
+            BlockSection::Tests => {
+                // output printed without a trailing newline ends up on the same line as the status, e.g. "printingFAILED"
+                // so a status at the end of a line only finishes the test if nothing else is printed before the next test
+                let next_is_boundary = match line_iter.peek() {
+                    Some((next, _)) => next.starts_with("test ") || *next == "failures:",
+                    None => true,
+                };
+                let finishing_status = |text: &str| match trailing_status.captures(text) {
+                    Some(capture) if capture["output"].is_empty() || next_is_boundary => {
+                        Some((capture["output"].to_string(), capture["status"].to_string()))
+                    }
+                    _ => None,
+                };
+
+                if line == "failures:" {
+                    section = BlockSection::Failures;
+                } else if line == "successes:" {
+                    section = BlockSection::Successes;
+                } else if let Some(capture) = slow_test.captures(line) {
+                    slow_tests.push((
+                        capture["name"].to_string(),
+                        capture["seconds"].parse::<u64>().unwrap_or(0),
+                    ));
+                } else if let Some(capture) = timed_out_test.captures(line) {
+                    // added by ptest when it kills the test binary, see crate::timeout
+                    let test_line = format!("test {}", &capture["name"]);
+                    let output = match unfinished_test.take() {
+                        Some((unfinished_line, output)) if unfinished_line == test_line => output,
+                        Some((unfinished_line, output)) => {
+                            unfinished_test = Some((unfinished_line, output));
+                            String::new()
+                        }
+                        None => String::new(),
+                    };
+
+                    match recover(
+                        finish_test(test_line, output, String::new(), "FAILED".to_string()),
+                        lenient,
+                        warnings,
+                    )? {
+                        Some(mut parsed_test) => {
+                            parsed_test.status = Status::TimedOut;
+                            parsed_test.add_error_reason(format!(
+                                "timed out after {} seconds",
+                                &capture["seconds"]
+                            ));
+                            parsed_tests.push(parsed_test);
+                        }
+                        None => unattributed_output += format!("{}\n", line).as_str(),
+                    }
+                } else if unfinished_test.is_some()
+                    && let Some((last_output, status)) = finishing_status(line)
+                {
+                    let (test_line, output) = unfinished_test.take().unwrap();
+                    match recover(finish_test(test_line.clone(), output.clone(), last_output, status), lenient, warnings)? {
+                        Some(res) => parsed_tests.push(res),
+                        None => unattributed_output += format!("{}\n{}{}\n", test_line, output, line).as_str(),
+                    }
+                } else if let Some(capture) = test_line_start.captures(line) {
+                    if let Some((_, output)) = unfinished_test.take() {
+                        unattributed_output += output.as_str();
+                    }
+
+                    let rest = capture.name("rest").map_or("", |x| x.as_str());
+
+                    let parsed = if rest.starts_with("bench:") {
+                        Some(ParsedTest::from_bench_line(line.to_string()))
+                    } else if finished_status.is_match(rest) {
+                        Some(ParsedTest::new(line.to_string()))
+                    } else if let Some((last_output, status)) = finishing_status(rest) {
+                        Some(finish_test(
+                            capture["test"].to_string(),
+                            String::new(),
+                            last_output,
+                            status,
+                        ))
+                    } else {
+                        None
+                    };
+
+                    if let Some(parsed) = parsed {
+                        match recover(parsed, lenient, warnings)? {
+                            Some(res) => parsed_tests.push(res),
+                            None => unattributed_output += format!("{}\n", line).as_str(),
+                        }
+                    } else {
+                        // the test is still running, anything after the "..." was printed by the test
+                        let output = if rest.is_empty() {
+                            String::new()
+                        } else {
+                            format!("{}\n", rest)
+                        };
+                        unfinished_test = Some((capture["test"].to_string(), output));
+                    }
+                } else {
+                    match unfinished_test.as_mut() {
+                        Some((_, output)) => *output += format!("{}\n", raw_line).as_str(),
+                        None => unattributed_output += format!("{}\n", raw_line).as_str(),
+                    }
+                }
+            }
+            BlockSection::Successes | BlockSection::Failures => {
+                if let Some(capture) = output_title.captures(line) {
+                    add_captured(&mut parsed_tests, &section, captured.take());
+                    captured = Some((capture["name"].to_string(), String::new()));
+                } else if line == "successes:" || line == "failures:" {
+                    // catches the second "successes:" or "failures:"
+                    add_captured(&mut parsed_tests, &section, captured.take());
+                    section = match section {
+                        BlockSection::Successes => BlockSection::SuccessList,
+                        _ => BlockSection::FailureList,
+                    };
+                } else if let Some((_, buffer)) = captured.as_mut() {
+                    *buffer += raw_line;
+                    *buffer += "\n";
+                }
+            }
+            // skip through the list of passed tests, the failures come after it
+            BlockSection::SuccessList => {
+                if line == "failures:" {
+                    section = BlockSection::Failures;
+                }
+            }
+            // skip through the list of failed tests after the second "failures:"
+            BlockSection::FailureList => {}
+        }
+    }
+
+    let timed_out = parsed_tests.iter().any(|x| x.status == Status::TimedOut);
+    let summary_printed = summary.is_some();
+
+    // the binary is only reported as crashed if ptest didn't kill it for timing out, see crate::timeout
+    if let Some(status) = crash_status
+        && !timed_out
+    {
+        if let Some((test_line, output)) = unfinished_test.take()
+            && let Some(mut parsed_test) = recover(
+                finish_test(
+                    test_line,
+                    String::new(),
+                    String::new(),
+                    "FAILED".to_string(),
+                ),
+                lenient,
+                warnings,
+            )?
+        {
+            parsed_test.add_error_reason(format!(
+                "{}the test binary crashed while this test was running ({})\n",
+                output, status
+            ));
+            parsed_tests.push(parsed_test);
+        }
+
+        *crashed = Some(Crash {
+            status,
+            missing: test_count.map_or(0, |x| x.saturating_sub(parsed_tests.len() as u32)),
+        });
+    }
+
+    let summary = match summary {
+        Some(res) => res,
+        // the test binary was killed or crashed so it never printed a summary
+        None if timed_out || crashed.is_some() => {
+            let mut res = Summary {
+                status: Status::Failed,
+                ..Default::default()
+            };
+            for test in &parsed_tests {
+                res += test.status.clone();
+            }
+            res
+        }
+        None => {
+            recover::<()>(
+                Err(ParseError::MissingSummary {
+                    group: group.full_path(),
+                }),
+                lenient,
+                warnings,
+            )?;
+
+            let mut res = Summary::default();
+            for test in &parsed_tests {
+                res += test.status.clone();
+            }
+            res.status = if res.failed > 0 {
+                Status::Failed
+            } else {
+                Status::Passed
             };
+            res
+        }
+    };
+
+    // the counts can only be checked if the binary ran to the end, see ParsedTestGroup::crashed for binaries that didn't
+    if !timed_out && crashed.is_none() {
+        let mut expected: Vec<(&'static str, u32)> = Vec::new();
 
-            // when the start of a block is found push the buffer with the previous block to blocks and start the new block
-            if buffer.len() > 0 {
-                blocks.push(
-                    RawTestGroup::new(buffer[0].clone(), buffer[1..].to_vec(), false)
-                        .map_err(|x| x)?,
-                )
+        if let Some(count) = test_count {
+            expected.push(("run", count));
+        }
+        if summary_printed {
+            expected.extend([
+                ("passed", summary.passed),
+                ("failed", summary.failed),
+                ("ignored", summary.ignored),
+                ("measured", summary.measured),
+            ]);
+        }
+
+        warnings.append(&mut check_counts(
+            &group.full_path(),
+            &expected,
+            &parsed_tests,
+        ));
+    }
+
+    for (name, seconds) in slow_tests {
+        for test in parsed_tests.iter_mut() {
+            if test.name() == name {
+                test.slow = Some(seconds);
             }
+        }
+    }
 
-            buffer = Vec::new();
+    // when the output isn't captured the failure reason is printed while the test runs rather than under "failures:"
+    for test in parsed_tests.iter_mut() {
+        if test.status == Status::Failed && test.error_reason.is_none() {
+            test.error_reason = test.output.clone();
+        }
+    }
 
-            if doc_test_beginning.is_match(next) {
-                reached_doc_tests = true;
-                buffer.push(x.trim().to_string());
-                continue;
+    Ok((parsed_tests, summary, unattributed_output))
+}
+
+/// Cross-checks the tests that were parsed against the counts printed in the output, so that a test that was skipped or misread is reported rather than silently giving the wrong results.
+/// The counts are `run` for the number of tests that ran and `passed`, `failed`, `ignored`, `measured` and `timed out` for the number with that status.
+/// Returns a [ParseError::CountMismatch] for each count that doesn't match.
+pub(crate) fn check_counts(
+    group: &str,
+    expected: &[(&'static str, u32)],
+    tests: &[ParsedTest],
+) -> Vec<ParseError> {
+    let count = |status: &Status| tests.iter().filter(|x| &x.status == status).count() as u32;
+
+    expected
+        .iter()
+        .filter_map(|(name, expected)| {
+            let found = match *name {
+                "run" => tests.iter().filter(|x| x.status != Status::Listed).count() as u32,
+                "passed" => count(&Status::Passed),
+                "failed" => count(&Status::Failed),
+                "ignored" => count(&Status::Ignored),
+                "measured" => count(&Status::Measured),
+                "timed out" => count(&Status::TimedOut),
+                _ => return None,
+            };
+
+            (found != *expected).then(|| ParseError::CountMismatch {
+                group: group.to_string(),
+                count: name,
+                expected: *expected,
+                found,
+            })
+        })
+        .collect()
+}
+
+/// A line of output that wasn't valid UTF-8, see [decode_output].
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidLine {
+    /// The line as it appears in the decoded output, with the invalid bytes replaced with U+FFFD.
+    pub text: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Decodes the output of cargo lossily so that a test printing invalid UTF-8, e.g. binary data or Latin-1 from a C library, doesn't stop the rest of the output from being parsed.
+/// The invalid bytes are replaced with U+FFFD, the lines they were on are returned so their raw bytes can be attached to the tests that printed them with [attach_raw_output].
+pub fn decode_output(bytes: &[u8]) -> (String, Vec<InvalidLine>) {
+    if let Ok(res) = std::str::from_utf8(bytes) {
+        return (res.to_string(), Vec::new());
+    }
+
+    let invalid_lines = bytes
+        .split(|x| *x == b'\n')
+        .map(|x| x.strip_suffix(b"\r").unwrap_or(x))
+        .filter(|x| std::str::from_utf8(x).is_err())
+        .map(|x| InvalidLine {
+            text: String::from_utf8_lossy(x).into_owned(),
+            bytes: x.to_vec(),
+        })
+        .collect();
+
+    (String::from_utf8_lossy(bytes).into_owned(), invalid_lines)
+}
+
+impl InvalidLine {
+    /// Gets the raw bytes of part of the line, start and end are byte offsets into [InvalidLine::text].
+    fn raw_bytes(&self, start: usize, end: usize) -> Vec<u8> {
+        let mut raw: Vec<u8> = Vec::new();
+        // the position in the text, each invalid sequence is a single U+FFFD in the text
+        let mut position = 0;
+
+        for chunk in self.bytes.utf8_chunks() {
+            let valid = chunk.valid().as_bytes();
+            let from = start.clamp(position, position + valid.len()) - position;
+            let to = end.clamp(position, position + valid.len()) - position;
+            raw.extend(&valid[from..to]);
+            position += valid.len();
+
+            if !chunk.invalid().is_empty() {
+                if position >= start && position < end {
+                    raw.extend(chunk.invalid());
+                }
+                position += '\u{FFFD}'.len_utf8();
             }
+        }
 
-            buffer.push(next.trim().to_string());
-            buffer.push(x.trim().to_string());
-        } else {
-            if buffer.len() > 0 {
-                buffer.push(x.trim().to_string());
+        raw
+    }
+}
+
+/// Sets [ParsedTest::raw_output] for the tests whose failure reason or output contains part of one of the invalid_lines from [decode_output].
+/// Output printed on the same line as the test's name is matched as well as whole lines.
+pub fn attach_raw_output(groups: &mut [ParsedTestGroup], invalid_lines: &[InvalidLine]) {
+    if invalid_lines.is_empty() {
+        return;
+    }
+
+    for test in groups.iter_mut().flat_map(|x| x.tests.iter_mut()) {
+        let text = match (&test.error_reason, &test.output) {
+            (Some(res), _) if res.contains('\u{FFFD}') => res,
+            (_, Some(res)) if res.contains('\u{FFFD}') => res,
+            _ => continue,
+        };
+
+        let mut raw: Vec<u8> = Vec::new();
+        let mut found = false;
+
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                raw.push(b'\n');
+            }
+
+            let invalid = invalid_lines.iter().find_map(|x| {
+                let start = x.text.find(line)?;
+                Some(x.raw_bytes(start, start + line.len()))
+            });
+
+            match invalid {
+                Some(res) if line.contains('\u{FFFD}') => {
+                    raw.extend(res);
+                    found = true;
+                }
+                _ => raw.extend(line.as_bytes()),
+            }
+        }
+
+        // a U+FFFD that was printed by the test rather than replacing an invalid byte isn't marked
+        if found {
+            test.raw_output = Some(raw);
+        }
+    }
+}
+
+/// Checks stderr for signs that the tests failed to build and extracts the errors reported by rustc.
+/// Returns None if the build succeeded.
+/// A build is considered failed if cargo reports that it could not compile a crate, or if there are errors and cargo never started running any tests.
+pub fn build_errors(stderr: &str) -> Option<Vec<CompileError>> {
+    let diagnostic_start =
+        Regex::new(r"^(?<level>error|warning)(\[(?<code>\w+)\])?: (?<message>.+)$").unwrap();
+    let location = Regex::new(r"^\s*--> (?<file_path>.+?):(?<line_num>\d+):(?<column>\d+)$").unwrap();
+
+    // cargo reports invalid args before it reads --color=never so they can still be coloured
+    let ansi_code = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    let windows_safe_err = ansi_code.replace_all(stderr, "").replace("\r", "");
+    let lines: Vec<&str> = windows_safe_err.split("\n").collect();
+
+    let could_not_compile = lines.iter().any(|x| x.starts_with("error: could not compile"));
+    let ran_tests = lines.iter().any(|x| {
+        x.trim().starts_with("Running ") || x.trim().starts_with("Doc-tests")
+    });
+
+    let mut errors: Vec<CompileError> = Vec::new();
+    let mut in_error = false;
+
+    for line in lines.iter() {
+        if let Some(capture) = diagnostic_start.captures(line) {
+            in_error = &capture["level"] == "error"
+                && !capture["message"].starts_with("could not compile")
+                && !capture["message"].starts_with("test failed")
+                && !capture["message"].starts_with("doctest failed");
+
+            if in_error {
+                errors.push(CompileError {
+                    code: capture.name("code").map(|x| x.as_str().to_string()),
+                    message: capture["message"].to_string(),
+                    file_path: None,
+                    line_number: None,
+                    column: None,
+                    package_name: None,
+                })
+            }
+        } else if in_error && let Some(capture) = location.captures(line) {
+            // only the first location belongs to the error, the rest are from notes and help messages
+            in_error = false;
+
+            if let Some(error) = errors.last_mut() {
+                error.file_path = Some(capture["file_path"].to_string());
+                error.line_number = capture["line_num"].parse::<u32>().ok();
+                error.column = capture["column"].parse::<u32>().ok();
             }
         }
     }
-    if reached_doc_tests {
-        blocks.push(RawTestGroup::new(String::new(), buffer, reached_doc_tests).map_err(|x| x)?);
+
+    if could_not_compile || (!ran_tests && !errors.is_empty()) {
+        Some(errors)
     } else {
-        if buffer.len() > 0 {
-            blocks.push(
-                RawTestGroup::new(buffer[0].clone(), buffer[1..].to_vec(), reached_doc_tests)
-                    .map_err(|x| x)?,
-            );
+        None
+    }
+}
+
+/// Interleaves the stdout and stderr of cargo test when they were captured separately, so they can be parsed as if they were written to the same pipe.
+/// Each `Running ...` or `Doc-tests ...` line from stderr is put before the next block of tests in stdout, along with anything cargo printed on stderr after it, e.g. the `process didn't exit successfully` line of a crashed binary.
+/// Anything printed on stderr before the first Running line, e.g. the compiler output, is put first.
+/// cargo only prints the Doc-tests line once for a crate but can run its Doc-tests in several blocks, these are kept in the same group while there are more blocks left than Doc-tests lines or until the `all doctests ran in` line that ends the merged Doc-tests of a crate.
+/// This can't always be right so the output should be captured from a single pipe where possible, as [crate::run::run] does.
+pub fn merge_streams(stdout: &str, stderr: &str) -> String {
+    let block_beginning = Regex::new(r"^running \d+ tests?$").unwrap();
+    let block_end = Regex::new(r"^(test result: .*|\d+ tests?, \d+ benchmarks?)$").unwrap();
+    let merged_doc_tests_end = Regex::new(r"^all doctests ran in ").unwrap();
+    let is_header =
+        |line: &str| line.trim().starts_with("Running ") || line.trim().starts_with("Doc-tests ");
+
+    let stdout = stdout.replace("\r", "");
+    let stderr = stderr.replace("\r", "");
+
+    // the lines before the first header, then each header with the lines printed after it
+    let mut before: Vec<&str> = Vec::new();
+    let mut headers: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in stderr.lines() {
+        if is_header(line) {
+            headers.push((line, Vec::new()));
+        } else {
+            match headers.last_mut() {
+                Some((_, lines)) => lines.push(line),
+                None => before.push(line),
+            }
         }
     }
 
-    if debug {
-        info!("\nOutput of merging stderr and stdout");
-        info!("Made {} blocks of tests", blocks.len());
-        for i in blocks.iter() {
-            println!("merge_outputs block: {}", i)
+    // a block starts at a running line or at the first line after the end of the last block, list output doesn't have a running line
+    let lines: Vec<&str> = stdout.lines().collect();
+    let mut starts: Vec<usize> = Vec::new();
+    let mut in_block = false;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || merged_doc_tests_end.is_match(trimmed) {
+            continue;
+        }
+
+        if !in_block || block_beginning.is_match(trimmed) {
+            starts.push(index);
+            in_block = true;
+        }
+        if block_end.is_match(trimmed) {
+            in_block = false;
         }
     }
 
-    Ok(blocks)
+    let mut merged: Vec<&str> = before;
+    let mut next_header = 0;
+    // whether the current group is a Doc-tests group that can still take more blocks
+    let mut open_doc_tests = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(block) = starts.iter().position(|x| *x == index) {
+            let blocks_left = starts.len() - block;
+            let headers_left = headers.len() - next_header;
+
+            if next_header < headers.len() && (!open_doc_tests || blocks_left <= headers_left) {
+                if next_header > 0 {
+                    merged.extend(headers[next_header - 1].1.iter());
+                }
+                merged.push(headers[next_header].0);
+                open_doc_tests = headers[next_header].0.trim().starts_with("Doc-tests ");
+                next_header += 1;
+            }
+        }
+
+        if merged_doc_tests_end.is_match(line.trim()) {
+            open_doc_tests = false;
+        }
+        merged.push(line);
+    }
+
+    // anything printed after the last block, and any binaries that didn't print anything to stdout
+    if next_header > 0 {
+        merged.extend(headers[next_header - 1].1.iter());
+    }
+    for (header, after) in headers.iter().skip(next_header) {
+        merged.push(header);
+        merged.extend(after.iter());
+    }
+
+    merged.join("\n") + "\n"
 }
 
-/// The main function for parsing the output of cargo test.
+/// The main function for parsing the output of cargo test, the output is the stdout and stderr of cargo test written to the same pipe, as [crate::run::run] does, so that the `Running ...` and `Doc-tests ...` lines from stderr are next to the tests they belong to.
+/// If stdout and stderr were captured separately they can be interleaved with [merge_streams] first.
 /// Recommended to use this via the run function rather that using it directly as the run function filters arguments out from cargo test that will affect this function.
-pub fn parse(
+pub fn parse_combined(output: String, cfg: Config) -> Result<TestOutcome, ParseError> {
+    parse_with_build(output, None, cfg)
+}
+
+/// The same as [parse_combined] but uses the artifacts from the build phase, see [crate::build::parse_build], to work out which package and target each group of tests is from.
+pub fn parse_with_build(
+    output: String,
+    build: Option<&BuildInfo>,
+    cfg: Config,
+) -> Result<TestOutcome, ParseError> {
+    if cfg.debug {
+        info!("Output");
+        println!("{}", output);
+    }
+
+    if let Some(errors) = build_errors(&output) {
+        return Ok(TestOutcome::BuildFailed(errors));
+    }
+
+    let mut parsed_groups: Vec<ParsedTestGroup> = Vec::new();
+    let groups =
+        merge_outputs(&output, build, cfg.debug, cfg.lenient).map_err(|x| x.locate(&output))?;
+
+    for group in groups {
+        let mut parsed_tests: Vec<ParsedTest> = Vec::new();
+        let mut summary: Option<Summary> = None;
+        let mut group_output = String::new();
+        let mut warnings = group.warnings.clone();
+        let mut crashed: Option<Crash> = None;
+
+        for block in split_blocks(&group.test_data) {
+            let (mut tests, block_summary, block_output) =
+                parse_block(block, &group, cfg.lenient, &mut warnings, &mut crashed)
+                    .map_err(|x| x.locate(&output))?;
+
+            parsed_tests.append(&mut tests);
+            group_output += block_output.as_str();
+            summary = Some(match summary {
+                Some(mut res) => {
+                    res += block_summary;
+                    res
+                }
+                None => block_summary,
+            });
+        }
+
+        parsed_groups.push(ParsedTestGroup {
+            crate_name: group.crate_name.clone(),
+            target: group.target.clone(),
+            file_path: group.file_path.clone(),
+            tests: parsed_tests,
+            summary,
+            output: if group_output.is_empty() { None } else { Some(group_output) },
+            warnings: warnings.into_iter().map(|x| x.locate(&output)).collect(),
+            crashed,
+        })
+    }
+
+    classify_failures(&mut parsed_groups);
+    parse_backtraces(&mut parsed_groups);
+
+    if let Some(kind) = &cfg.doc_kind {
+        filter_doc_kind(&mut parsed_groups, kind);
+    }
+
+    Ok(TestOutcome::Tests(parsed_groups))
+}
+
+/// Parses the output of `cargo test -- --list` into [ParsedTestGroup]s, every test will have the status [Status::Listed].
+/// The stdout of `cargo test -- --list --ignored` can be passed as ignored_stdout to mark the ignored tests with [Status::Ignored], pass an empty string to skip this.
+/// The groups will not have a summary as no tests were run.
+/// stdout and stderr are interleaved with [merge_streams], the artifacts from the build phase can be passed in the same way as [parse_with_build].
+pub fn parse_list(
     stdout: String,
+    ignored_stdout: String,
     stderr: String,
+    build: Option<&BuildInfo>,
+    cfg: Config,
+) -> Result<TestOutcome, ParseError> {
+    let ignored_output = if ignored_stdout.is_empty() {
+        String::new()
+    } else {
+        merge_streams(&ignored_stdout, &stderr)
+    };
+
+    parse_list_combined(merge_streams(&stdout, &stderr), ignored_output, build, cfg)
+}
+
+/// The same as [parse_list] but the output of each command has stdout and stderr combined, like [parse_combined].
+pub fn parse_list_combined(
+    output: String,
+    ignored_output: String,
+    build: Option<&BuildInfo>,
     cfg: Config,
-) -> Result<Vec<ParsedTestGroup>, ParseError> {
+) -> Result<TestOutcome, ParseError> {
     if cfg.debug {
-        info!("Stdout");
-        println!("{}\n\n", stdout);
-        info!("Stderr");
-        println!("{}", stderr);
+        info!("Output");
+        println!("{}\n\n", output);
+        info!("Ignored output");
+        println!("{}", ignored_output);
     }
 
-    // regex
-    let test_block_start_match = Regex::new(r"running (?<count>\d+) test(s?)").unwrap();
-    let doc_test_line = Regex::new(r"test (?<file_path>[\w/\\.]+) -( (?<module_path>[\w/:]+))? \(line (?<line_num>\d+)\)( - (?<note>[\w\s]+))? \.\.\. (?<status>\w+)").unwrap();
+    if let Some(errors) = build_errors(&output) {
+        return Ok(TestOutcome::BuildFailed(errors));
+    }
+
+    let list_end = Regex::new(r"^\d+ tests?, \d+ benchmarks?$").unwrap();
+    // merged Doc-tests print how long they took even when only listing them
+    let merged_doc_tests =
+        Regex::new(r"^all doctests ran in [\d.]+s; merged doctests compilation took [\d.]+s$")
+            .unwrap();
+
+    let groups =
+        merge_outputs(&output, build, cfg.debug, cfg.lenient).map_err(|x| x.locate(&output))?;
+    let ignored_groups = if ignored_output.is_empty() {
+        Vec::new()
+    } else {
+        merge_outputs(&ignored_output, build, cfg.debug, cfg.lenient)
+            .map_err(|x| x.locate(&ignored_output))?
+    };
 
     let mut parsed_groups: Vec<ParsedTestGroup> = Vec::new();
-    let groups = merge_outputs(stdout, stderr, cfg.debug).map_err(|x| x)?;
 
-    for group in groups {
-        let mut failed = 0;
+    for (index, group) in groups.iter().enumerate() {
+        let ignored = match ignored_groups.get(index) {
+            Some(res) => res.test_data.clone(),
+            None => Vec::new(),
+        };
 
-        let mut line_iter = group.test_data.iter().map(|x| x.as_str());
         let mut parsed_tests: Vec<ParsedTest> = Vec::new();
-        if group.test_type == TestType::Doc {
-            // when parsing doc tests just look for lines starting with test and parse them
-            loop {
-                let line = match get_next(&mut line_iter) {
-                    Some(res) => res,
-                    None => break,
-                };
+        let mut warnings = group.warnings.clone();
 
-                if doc_test_line.is_match(line) {
-                    parsed_tests.push(ParsedTest::new(line.to_string()).map_err(|x| x)?)
-                }
+        for line in group.test_data.iter().map(|x| x.trim()) {
+            if list_end.is_match(line) || merged_doc_tests.is_match(line) {
+                continue;
             }
 
-            let summary = summarise_doctests(parsed_tests.clone());
+            let parsed = ParsedTest::from_list_line(
+                line.to_string(),
+                ignored.iter().any(|x| x.trim() == line),
+            );
+            if let Some(res) = recover(parsed, cfg.lenient, &mut warnings).map_err(|x| x.locate(&output))? {
+                parsed_tests.push(res);
+            }
+        }
 
-            parsed_groups.push(ParsedTestGroup {
-                crate_name: group.crate_name.clone(),
-                file_path: group.file_path.clone(),
-                tests: parsed_tests,
-                summary: Some(summary),
-            })
-        } else {
-            let test_block_start = match get_next(&mut line_iter) {
-                Some(res) => res,
-                None => break,
-            };
+        parsed_groups.push(ParsedTestGroup {
+            crate_name: group.crate_name.clone(),
+            target: group.target.clone(),
+            file_path: group.file_path.clone(),
+            tests: parsed_tests,
+            summary: None,
+            output: None,
+            warnings: warnings.into_iter().map(|x| x.locate(&output)).collect(),
+            crashed: None,
+        })
+    }
 
-            let capture = match test_block_start_match.captures(test_block_start) {
-                Some(res) => res,
-                None => break,
-            };
-            let count_str = &capture["count"];
+    if let Some(kind) = &cfg.doc_kind {
+        filter_doc_kind(&mut parsed_groups, kind);
+    }
 
-            let count: usize = match count_str.parse::<usize>() {
-                Ok(res) => res,
-                Err(_) => {
-                    return parse_error!(
-                        "Could not convert the captured number from a test block, got: {}",
-                        count_str
-                    );
-                }
-            };
-            // parse each test line
-            for _ in 0..count {
-                let line = match get_next(&mut line_iter) {
-                    Some(res) => res,
-                    None => break,
-                };
-                let parsed_test = ParsedTest::new(line.to_string()).map_err(|x| x)?;
-                if parsed_test.status == Status::Failed {
-                    failed += 1
-                }
-                parsed_tests.push(parsed_test);
-            }
+    Ok(TestOutcome::Tests(parsed_groups))
+}
 
-            // parse the error reasons under "failures:" and add them to the correct tests
-            let mut in_failure_block = false;
-
-            if failed > 0 {
-                let mut add_to_buffer = false;
-                let mut buffer = String::new();
-                let mut name = String::new();
-                let failure_title =
-                    Regex::new(r"---- (?<path>[\w:_]+) (?<channel>\w+) ----").unwrap();
-                loop {
-                    let line_option = get_next(&mut line_iter);
-                    let line = match line_option {
-                        Some(res) => res,
-                        None => break,
-                    }
-                    .trim();
+/// Sets the [FailureKind] of every failed test from its failure reason and notes.
+pub(crate) fn classify_failures(groups: &mut [ParsedTestGroup]) {
+    for test in groups.iter_mut().flat_map(|x| x.tests.iter_mut()) {
+        if test.status == Status::Failed {
+            test.failure_kind = Some(FailureKind::classify(test));
+        }
+    }
+}
 
-                    if failure_title.is_match(line) {
-                        add_to_buffer = true;
+/// Splits a failure reason around the backtrace in it, returning the lines before the `stack backtrace:` line, the lines of the frames and the lines after the last frame.
+pub(crate) fn split_backtrace(reason: &str) -> Option<(Vec<&str>, Vec<&str>, Vec<&str>)> {
+    let frame = Regex::new(r"^\s*(\d+:\s|at\s)").unwrap();
+    let lines: Vec<&str> = reason.lines().collect();
+
+    let start = lines.iter().position(|x| x.trim() == "stack backtrace:")?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|x| !frame.is_match(x))
+        .map_or(lines.len(), |x| start + 1 + x);
+
+    Some((
+        lines[..start].to_vec(),
+        lines[start + 1..end].to_vec(),
+        lines[end..].to_vec(),
+    ))
+}
 
-                        if buffer.len() != 0 {
-                            for i in &mut parsed_tests {
-                                if i.module_path == name {
-                                    i.add_error_reason(buffer.clone())
-                                }
-                            }
-                        }
+/// Parses the frames of a backtrace, a frame is a line with its index and function, e.g. `4: cargo_ptest::tests::panic`, optionally followed by a line with its location, e.g. `at ./src/tests.rs:8:5`.
+/// With `RUST_BACKTRACE=full` the function is preceded by its address, e.g. `4:     0x55d0c3b3e1aa - cargo_ptest::tests::panic::h0123456789abcdef`.
+/// The frames from the crates in `crates`, or with a relative path, are marked as being in the workspace.
+fn parse_frames(lines: &[&str], crates: &[String]) -> Vec<Frame> {
+    let function =
+        Regex::new(r"^\s*(?<index>\d+):\s+(0x[0-9a-f]+ - )?(?<function>.+?)(::h[0-9a-f]{16})?$")
+            .unwrap();
+    let location = Regex::new(r"^\s*at (?<file>.+?)(:(?<line>\d+))?(:(?<column>\d+))?$").unwrap();
+
+    let mut frames: Vec<Frame> = Vec::new();
+
+    for line in lines {
+        if let Some(capture) = function.captures(line) {
+            frames.push(Frame {
+                index: capture["index"].parse().unwrap_or_default(),
+                function: capture["function"].to_string(),
+                file: None,
+                line: None,
+                column: None,
+                in_workspace: false,
+            });
+        } else if let Some(capture) = location.captures(line)
+            && let Some(frame) = frames.last_mut()
+        {
+            frame.file = Some(capture["file"].to_string());
+            frame.line = capture.name("line").and_then(|x| x.as_str().parse().ok());
+            frame.column = capture.name("column").and_then(|x| x.as_str().parse().ok());
+        }
+    }
 
-                        name = match failure_title.captures(line) {
-                            Some(res) => res,
-                            None => break,
-                        }["path"]
-                            .to_string();
-
-                        buffer = String::new();
-                    } else if line.starts_with("failures:") {
-                        // catches the second "failures:"
-                        if in_failure_block {
-                            for i in &mut parsed_tests {
-                                if i.module_path == name {
-                                    i.add_error_reason(buffer.clone())
-                                }
-                            }
-                            break;
-                        } else {
-                            in_failure_block = true;
-                        }
-                    } else {
-                        if add_to_buffer {
-                            buffer += line;
-                            buffer += "\n";
-                        }
-                    }
-                }
+    for frame in frames.iter_mut() {
+        // trait impls start with a <, e.g. <cargo_ptest::Item as core::fmt::Debug>::fmt
+        // and full backtraces add a hash to the crate name, e.g. std[e28293b1aa0f68bd]::panicking
+        let crate_name = frame
+            .function
+            .trim_start_matches('<')
+            .split(['[', ':'])
+            .next()
+            .unwrap_or_default();
+
+        frame.in_workspace = crates.iter().any(|x| x == crate_name)
+            || frame
+                .file
+                .as_ref()
+                .is_some_and(|x| std::path::Path::new(x).is_relative());
+    }
 
-                // skip through the list of failed tests after the second "failures:"
-                for _ in 0..failed {
-                    let _ = get_next(&mut line_iter);
-                }
-            }
+    frames
+}
 
-            let summary = match get_next(&mut line_iter) {
-                Some(res) => res,
-                None => {
-                    return parse_error!(
-                        "Could not extract summary data for {}",
-                        group.full_path()
-                    );
-                }
-            };
-            parsed_groups.push(ParsedTestGroup {
-                crate_name: group.crate_name.clone(),
-                file_path: group.file_path.clone(),
-                tests: parsed_tests,
-                summary: Some(Summary::new(summary).map_err(|x| x)?),
-            })
+/// Parses the backtraces in the failure reasons of the failed and timed out tests, see [ParsedTest::backtrace].
+/// The crates of the groups are the workspace's own crates, the other frames are from std or dependencies.
+pub(crate) fn parse_backtraces(groups: &mut [ParsedTestGroup]) {
+    let crates: Vec<String> = groups.iter().map(|x| x.crate_name.clone()).collect();
+
+    for test in groups.iter_mut().flat_map(|x| x.tests.iter_mut()) {
+        if let Some((_, lines, _)) = test.error_reason.as_deref().and_then(split_backtrace) {
+            test.backtrace = parse_frames(&lines, &crates);
+        }
+    }
+}
+
+/// Keeps only the Doc-tests of the given kind, see [Config::doc_kind]. Groups left without any tests are removed.
+/// The summaries are recounted from the tests that are left, the tests that were removed are counted as filtered.
+pub fn filter_doc_kind(groups: &mut Vec<ParsedTestGroup>, kind: &DocTestKind) {
+    for group in groups.iter_mut() {
+        let count = group.tests.len();
+        group.tests.retain(|x| x.doc_kind.as_ref() == Some(kind));
+
+        if let Some(summary) = group.summary.as_mut() {
+            summary.recount(&group.tests);
+            summary.filtered += (count - group.tests.len()) as u32;
         }
     }
 
-    Ok(parsed_groups)
+    groups.retain(|x| !x.tests.is_empty());
 }
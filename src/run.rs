@@ -1,50 +1,141 @@
-use crate::config::{config, Config};
-use crate::parse::{parse, ParsedTestGroup};
+use crate::bench::{baseline_path, compare_baseline, save_baseline};
+use crate::build::{metadata_target_dir, parse_build, BuildInfo};
+use crate::config::{self, project_config, BacktraceStyle, Config, RunnerType};
+use crate::display::{target_label, test_key, DisplayType};
+use crate::nextest::parse_nextest;
+use crate::parse::{
+    attach_raw_output, build_errors, decode_output, parse_combined, parse_list, parse_with_build,
+    CompileError, InvalidLine, ParseError, ParsedTestGroup, Status, TestOutcome,
+};
+use crate::timeout::Watchdog;
+use crate::watch::{failed_tests, filter_args};
 use std::fmt::Formatter;
+use std::io::{BufRead, BufReader, Read};
 use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
 
-macro_rules! run_error {
-    ($($arg:tt)*) => {
-        Err(RunError {error: format!("Run Error: {}", format!($($arg)*))})
-    };
-}
-
-pub struct RunError {
-    pub error: String,
+/// An error from running the tests, see [run].
+#[derive(Debug)]
+pub enum RunError {
+    /// ptest's args or the project config are invalid.
+    Config(String),
+    /// cargo could not be started, e.g. because it isn't on the PATH.
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    /// Reading the output of cargo, or the output read by [report], failed.
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// The output of cargo test could not be parsed.
+    Parse(ParseError),
+    /// The tests failed to build. [run] returns [TestOutcome::BuildFailed] so the errors can be displayed, this is only returned by [TestOutcome::into_tests].
+    BuildFailed(Vec<CompileError>),
+    /// A benchmark baseline could not be saved or compared against.
+    Baseline(String),
+    /// The terminal doesn't support the terminal UI.
+    Terminal(String),
 }
 
 impl std::fmt::Display for RunError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "\x1b[31m{}\x1b[0m", self.error)
+        match self {
+            RunError::Config(err) | RunError::Baseline(err) | RunError::Terminal(err) => {
+                write!(f, "{}", err)
+            }
+            RunError::Spawn { command, .. } => {
+                write!(f, "'cargo {}' could not be started", command)
+            }
+            RunError::Io { context, .. } => write!(f, "{}", context),
+            RunError::Parse(_) => write!(f, "the output of cargo could not be parsed"),
+            RunError::BuildFailed(errors) => {
+                write!(f, "the tests failed to build with {} errors", errors.len())
+            }
+        }
+    }
+}
+
+/// The cause of [RunError::Spawn], [RunError::Io] and [RunError::Parse] is only returned by `source` and isn't part of the message, see [RunError::with_sources].
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Spawn { source, .. } | RunError::Io { source, .. } => Some(source),
+            RunError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl RunError {
+    /// The message followed by the errors that caused it, e.g. `could not read stdin: Broken pipe (os error 32)`, used when the error is shown to the user.
+    pub fn with_sources(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+
+        while let Some(err) = source {
+            message += &format!(": {}", err);
+            source = err.source();
+        }
+
+        message
+    }
+}
+
+impl From<ParseError> for RunError {
+    fn from(value: ParseError) -> Self {
+        RunError::Parse(value)
     }
 }
 
 fn help() {
-    println!("ptest help");
-    println!("run using \x1b[34mcargo ptest\x1b[0m");
-    println!("all arguments passed to cargo ptest are forwarded to cargo test");
+    println!("{}", config::help());
+    println!();
     println!("below is the help output for cargo test");
     println!();
 }
 
-/// Runs cargo test and automatically parses the output into [ParsedTestGroup] to be passed onto [Display].
-/// WIP
-/// Currently only works when being run as a binary, once completed you will be able to pass a [Config] but currently all configuration is done through fetching the command args
-/// You can configure how `cargo test` is run using the cmd_args parameter, if cmd_args is None then it assumes you are running it as a binary and uses std::env::args() instead
-pub fn run(
-    _cfg: Option<Config>,
-    cmd_args: Option<Vec<String>>,
-) -> Result<Vec<ParsedTestGroup>, RunError> {
-    let is_lib = cmd_args.is_some();
+/// Splits the command line args of the binary into the [Config] for ptest and the args that should be forwarded to cargo test.
+/// The args are applied on top of the project config, see [project_config].
+pub fn binary_args() -> Result<(Config, Vec<String>), RunError> {
+    // when running cargo ptest the args look like ["C:\\Users\\user\\.cargo\\bin\\cargo-ptest.exe", "ptest", ...]
+    // when running cargo-ptest the args look like ["cargo-ptest", ...]
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|x| x == "ptest") {
+        args.remove(0);
+    }
 
-    let mut cfg: Config;
+    let mut consume_args: Vec<String> = Vec::new();
     let mut forward_args: Vec<String> = Vec::new();
 
-    // filter out the --no-capture args as it makes the output of the cargo test command unpredictable and messes with the parser
-    // verbose also messes up parsing so it gets filtered out
+    let mut passed_forward_point: bool = false;
+
+    args.into_iter().for_each(|x| {
+        if passed_forward_point {
+            forward_args.push(x)
+        } else if x == "--" {
+            passed_forward_point = true;
+        } else {
+            consume_args.push(x);
+        }
+    });
+
+    match project_config(consume_args, forward_args) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(RunError::Config(err)),
+    }
+}
+
+/// Gets the config and the filtered args to pass to cargo test, if cmd_args is None then they are taken from the binary's args.
+fn prepare(
+    cfg: Option<Config>,
+    cmd_args: Option<Vec<String>>,
+) -> Result<(Config, Vec<String>), RunError> {
+    // verbose messes up parsing so it gets filtered out
     // remove any color so I can set color=never to avoid having to deal with ansi codes all over the place
     let filter_list = [
-        "--nocapture",
         "-v",
         "--verbose",
         "--color=always",
@@ -52,62 +143,42 @@ pub fn run(
         "--color=never",
     ];
 
-    if is_lib {
-        let args = cmd_args.unwrap();
-
-        args.into_iter().for_each(|x| {
-            if !filter_list.contains(&x.as_str()) {
-                forward_args.push(x)
-            }
-        });
-
-        forward_args.push("--color=never".to_string());
-
-        cfg = _cfg.unwrap_or(Config::default())
-    } else {
-        let unfiltered_args: Vec<String> = std::env::args().collect();
+    let (cfg, args) = match cmd_args {
+        Some(args) => (cfg.unwrap_or_default(), args),
+        None => binary_args()?,
+    };
 
-        let args: Vec<String>;
-        let mut consume_args: Vec<String> = Vec::new();
+    let mut forward_args: Vec<String> = args
+        .into_iter()
+        .filter(|x| !filter_list.contains(&x.as_str()))
+        .collect();
 
-        // when running cargo ptest the args look like ["C:\\Users\\user\\.cargo\\bin\\cargo-ptest.exe", "ptest", ...]
-        // when running cargo-ptest the args look like ["cargo-ptest"]
+    // --color=never has to go before the args for the test binaries otherwise cargo itself will still use color
+    let cargo_args_end = forward_args
+        .iter()
+        .position(|x| x == "--")
+        .unwrap_or(forward_args.len());
+    forward_args.insert(cargo_args_end, "--color=never".to_string());
 
-        if unfiltered_args[0] == "cargo-ptest" {
-            args = unfiltered_args[1..].to_vec();
-        } else if unfiltered_args[1] == "ptest" {
-            args = unfiltered_args[2..].to_vec();
-        } else {
-            return run_error!("how did you manage to see this error");
+    // nextest has its own option for showing the output of passed tests, see Nextest::args
+    if cfg.show_output
+        && cfg.runner == RunnerType::Cargo
+        && !forward_args.contains(&"--show-output".to_string())
+    {
+        if !forward_args.contains(&"--".to_string()) {
+            forward_args.push("--".to_string());
         }
-
-        let mut passed_forward_point: bool = false;
-
-        args.into_iter().for_each(|x| {
-            if passed_forward_point {
-                if !filter_list.contains(&x.as_str()) && (&cmd_args).is_none() {
-                    forward_args.push(x)
-                }
-            } else {
-                if x == "--" {
-                    passed_forward_point = true;
-                } else {
-                    consume_args.push(x);
-                }
-            }
-        });
-
-        forward_args.push("--color=never".to_string());
-
-        cfg = match config(consume_args) {
-            Ok(res) => res,
-            Err(err) => return Err(RunError { error: err }),
-        };
+        forward_args.push("--show-output".to_string());
     }
 
+    Ok((cfg, forward_args))
+}
+
+/// Runs cargo with the given command (test or bench) and args and returns the stdout and stderr.
+fn cargo_test(command: &str, forward_args: &[String]) -> Result<(String, String), RunError> {
     let cmd_result = Command::new("cargo")
-        .arg("test")
-        .args(&forward_args)
+        .arg(command)
+        .args(forward_args)
         .env("CARGO_TERM_COLOR", "always")
         .env("FORCE_COLOR", "1")
         .stdout(Stdio::piped())
@@ -116,29 +187,609 @@ pub fn run(
 
     let cmd = match cmd_result {
         Ok(res) => res,
-        Err(e) => return run_error!("'cargo test' failed: {}", e.to_string()),
+        Err(e) => {
+            return Err(RunError::Spawn {
+                command: command.to_string(),
+                source: e,
+            });
+        }
     };
 
-    let stdout = match String::from_utf8(cmd.stdout) {
+    Ok((
+        String::from_utf8_lossy(&cmd.stdout).into_owned(),
+        String::from_utf8_lossy(&cmd.stderr).into_owned(),
+    ))
+}
+
+/// Runs cargo with the given command (test or bench) and args and returns stdout and stderr combined.
+/// Both are written to the same pipe so the `Running ...` and `Doc-tests ...` lines from stderr stay next to the output of the tests they belong to.
+/// If a [Watchdog] is passed then the output is read line by line as it is streamed so tests that run for too long can be reported, see [crate::timeout].
+/// The output is decoded lossily, the lines that weren't valid UTF-8 are returned as well, see [decode_output].
+/// If a [BacktraceStyle] is passed then `RUST_BACKTRACE` is set for the tests, otherwise it is inherited.
+fn cargo_test_combined(
+    command: &str,
+    forward_args: &[String],
+    watchdog: Option<Watchdog>,
+    backtrace: Option<&BacktraceStyle>,
+) -> Result<(String, Vec<InvalidLine>), RunError> {
+    let (mut reader, writer) = match std::io::pipe() {
         Ok(res) => res,
-        Err(_) => return run_error!("failed to parse stdout from utf8"),
+        Err(e) => {
+            return Err(RunError::Io {
+                context: format!("failed to create pipe for 'cargo {}'", command),
+                source: e,
+            });
+        }
     };
 
-    let stderr = match String::from_utf8(cmd.stderr) {
+    let writer_clone = match writer.try_clone() {
+        Ok(res) => res,
+        Err(e) => {
+            return Err(RunError::Io {
+                context: format!("failed to create pipe for 'cargo {}'", command),
+                source: e,
+            });
+        }
+    };
+
+    let mut cmd = Command::new("cargo");
+    cmd.arg(command)
+        .args(forward_args)
+        .env("CARGO_TERM_COLOR", "always")
+        .env("FORCE_COLOR", "1");
+
+    if let Some(backtrace) = backtrace {
+        cmd.env("RUST_BACKTRACE", backtrace.env_value());
+    }
+
+    // the command has to be dropped before reading so the pipe is closed once cargo exits
+    let child_result = cmd.stdout(writer_clone).stderr(writer).spawn();
+    drop(cmd);
+
+    let mut child = match child_result {
         Ok(res) => res,
-        Err(_) => return run_error!("failed to parse stderr from utf8"),
+        Err(e) => {
+            return Err(RunError::Spawn {
+                command: command.to_string(),
+                source: e,
+            });
+        }
     };
 
+    let mut output: Vec<u8> = Vec::new();
+
+    match watchdog {
+        Some(mut watchdog) => {
+            let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(reader);
+
+                loop {
+                    let mut line: Vec<u8> = Vec::new();
+                    match reader.read_until(b'\n', &mut line) {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {
+                            if sender.send(line).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+
+            loop {
+                match receiver.recv_timeout(Duration::from_millis(500)) {
+                    Ok(line) => {
+                        watchdog.line(String::from_utf8_lossy(&line).as_ref());
+                        output.extend(line);
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+
+                let (lines, kill) = watchdog.check();
+
+                for line in lines {
+                    if !output.is_empty() && !output.ends_with(b"\n") {
+                        output.push(b'\n');
+                    }
+                    output.extend(line.into_bytes());
+                    output.push(b'\n');
+                }
+
+                // the output of a test binary that outlived cargo isn't waited for
+                if kill && watchdog.kill_test_binary(&mut child) {
+                    break;
+                }
+            }
+        }
+        None => {
+            if let Err(e) = reader.read_to_end(&mut output) {
+                return Err(RunError::Io {
+                    context: format!("failed to read the output of 'cargo {}'", command),
+                    source: e,
+                });
+            }
+        }
+    }
+
+    if let Err(e) = child.wait() {
+        return Err(RunError::Io {
+            context: format!("failed to wait for 'cargo {}'", command),
+            source: e,
+        });
+    }
+
+    Ok(decode_output(&output))
+}
+
+/// A tool that runs the tests and parses its output into [crate::parse::ParsedTestGroup]s, chosen with `--runner`.
+/// The tests are always built with `cargo test --no-run` first so that build errors and the targets of the test binaries are known, see [build].
+pub trait Runner {
+    /// The cargo subcommand used to run the tests, e.g. test for `cargo test`.
+    fn command(&self) -> &'static str;
+    /// Converts the args forwarded by ptest into the args passed after the subcommand.
+    fn args(&self, forward_args: &[String], cfg: &Config) -> Vec<String>;
+    /// The args used to build the tests with `cargo test --no-run`.
+    fn build_args(&self, forward_args: &[String]) -> Vec<String>;
+    /// Whether the output can be followed by the [Watchdog] for --timeout and --hard-timeout.
+    fn supports_timeouts(&self) -> bool;
+    fn parse(
+        &self,
+        output: String,
+        build: &BuildInfo,
+        cfg: Config,
+    ) -> Result<TestOutcome, ParseError>;
+}
+
+/// Runs the tests with `cargo test`.
+pub struct CargoTest;
+
+impl Runner for CargoTest {
+    fn command(&self) -> &'static str {
+        "test"
+    }
+
+    fn args(&self, forward_args: &[String], _cfg: &Config) -> Vec<String> {
+        forward_args.to_vec()
+    }
+
+    fn build_args(&self, forward_args: &[String]) -> Vec<String> {
+        forward_args.to_vec()
+    }
+
+    fn supports_timeouts(&self) -> bool {
+        true
+    }
+
+    fn parse(
+        &self,
+        output: String,
+        build: &BuildInfo,
+        cfg: Config,
+    ) -> Result<TestOutcome, ParseError> {
+        parse_with_build(output, Some(build), cfg)
+    }
+}
+
+/// Runs the tests with `cargo nextest run`, nextest has to be installed separately.
+/// nextest doesn't run Doc-tests and handles its own timeouts with the slow-timeout option in its config.
+pub struct Nextest;
+
+impl Runner for Nextest {
+    fn command(&self) -> &'static str {
+        "nextest"
+    }
+
+    fn args(&self, forward_args: &[String], cfg: &Config) -> Vec<String> {
+        let mut args = vec!["run".to_string()];
+        let cargo_args_end = forward_args
+            .iter()
+            .position(|x| x == "--")
+            .unwrap_or(forward_args.len());
+
+        args.extend(forward_args[..cargo_args_end].iter().cloned());
+
+        if cfg.show_output {
+            args.push("--success-output=immediate".to_string());
+        }
+
+        args.extend(forward_args[cargo_args_end..].iter().cloned());
+        args
+    }
+
+    /// Only the args that select what to build are passed on, the rest are for nextest.
+    fn build_args(&self, forward_args: &[String]) -> Vec<String> {
+        let flags = [
+            "--workspace",
+            "--all",
+            "--lib",
+            "--bins",
+            "--tests",
+            "--benches",
+            "--examples",
+            "--all-targets",
+            "--all-features",
+            "--no-default-features",
+            "--release",
+            "--frozen",
+            "--locked",
+            "--offline",
+            "--color=never",
+        ];
+        let value_flags = [
+            "-p",
+            "--package",
+            "--exclude",
+            "--bin",
+            "--test",
+            "--bench",
+            "--example",
+            "-F",
+            "--features",
+            "--target",
+            "--target-dir",
+            "--manifest-path",
+            "--cargo-profile",
+        ];
+
+        let mut build_args: Vec<String> = Vec::new();
+        let mut args = forward_args.iter().take_while(|x| *x != "--");
+
+        while let Some(arg) = args.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+
+            if flags.contains(&arg.as_str()) {
+                build_args.push(arg.clone());
+            } else if value_flags.contains(&name) {
+                let value = match value {
+                    Some(res) => res,
+                    None => match args.next() {
+                        Some(res) => res.clone(),
+                        None => continue,
+                    },
+                };
+
+                // --profile is the nextest profile, the cargo profile is set with --cargo-profile
+                let name = if name == "--cargo-profile" {
+                    "--profile"
+                } else {
+                    name
+                };
+                build_args.push(format!("{}={}", name, value));
+            }
+        }
+
+        build_args
+    }
+
+    fn supports_timeouts(&self) -> bool {
+        false
+    }
+
+    fn parse(
+        &self,
+        output: String,
+        build: &BuildInfo,
+        cfg: Config,
+    ) -> Result<TestOutcome, ParseError> {
+        parse_nextest(output, Some(build), cfg)
+    }
+}
+
+/// Gets the [Runner] for the --runner option.
+pub fn runner(runner_type: &RunnerType) -> Box<dyn Runner> {
+    match runner_type {
+        RunnerType::Cargo => Box::new(CargoTest),
+        RunnerType::Nextest => Box::new(Nextest),
+    }
+}
+
+/// Builds the tests with `cargo test --no-run --message-format=json` so that each test binary can be mapped to the package and target it was built from.
+/// The command is either test or bench, benchmarks are built with `cargo bench` so that the release profile is used.
+/// Only the args for cargo are used, the args after `--` are for the test binaries.
+/// If the build fails and cargo didn't report any errors as JSON (e.g. the manifest is invalid) then the errors are taken from stderr.
+/// cargo can't combine `--doc` with `--no-run` so the library is built with `--lib` instead, the Doc-tests are mapped to its artifact.
+fn build(command: &str, forward_args: &[String]) -> Result<BuildInfo, RunError> {
+    let mut build_args: Vec<String> = vec!["--no-run".to_string(), "--message-format=json".to_string()];
+    for arg in forward_args.iter().take_while(|x| *x != "--") {
+        build_args.push(if arg == "--doc" {
+            "--lib".to_string()
+        } else {
+            arg.clone()
+        });
+    }
+
+    let (stdout, stderr) = cargo_test(command, &build_args)?;
+    let mut build = parse_build(&stdout);
+
+    if !build.success && build.errors.is_empty() {
+        build.errors = build_errors(&stderr).unwrap_or_default();
+    }
+
+    Ok(build)
+}
+
+/// Runs cargo test and automatically parses the output into [ParsedTestGroup] to be passed onto [Display].
+/// If the tests fail to build then [TestOutcome::BuildFailed] is returned with the errors from rustc.
+/// cmd_args are the args passed to cargo test, with the args for the test binaries after a `--`, and cfg configures ptest, [Config::default] is used if it is None.
+/// If cmd_args is None then both are read from the binary's args with [binary_args] and cfg is ignored.
+pub fn run(cfg: Option<Config>, cmd_args: Option<Vec<String>>) -> Result<TestOutcome, RunError> {
+    let (cfg, forward_args) = prepare(cfg, cmd_args)?;
+    let runner = runner(&cfg.runner);
+
     if forward_args.contains(&"--help".to_string()) || forward_args.contains(&"-h".to_string()) {
+        let (stdout, _) = cargo_test(runner.command(), &runner.args(&forward_args, &cfg))?;
         help();
         println!("{}", stdout);
-        return Ok(Vec::new());
+        return Ok(TestOutcome::Tests(Vec::new()));
     }
 
-    let parsed = match parse(stdout, stderr, cfg) {
-        Ok(res) => res,
+    let mut groups = match run_tests(&cfg, &forward_args)? {
+        TestOutcome::Tests(res) => res,
+        res => return Ok(res),
+    };
+
+    for attempt in 1..=cfg.retries {
+        let failed_keys: Vec<String> = groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .tests
+                    .iter()
+                    .filter(|x| x.status == Status::Failed || x.status == Status::TimedOut)
+                    .map(move |x| test_key(group, x))
+            })
+            .collect();
+
+        if failed_keys.is_empty() {
+            break;
+        }
+
+        let mut retried = match run_tests(&cfg, &filter_args(&forward_args, &failed_tests(&groups)))? {
+            TestOutcome::Tests(res) => res,
+            // the tests were built for the first run so this shouldn't happen, the results of the first run are kept
+            TestOutcome::BuildFailed(_) => break,
+        };
+
+        // the filters aren't exact so only the tests that failed are replaced
+        for group in retried.iter_mut() {
+            let keys: Vec<String> = group.tests.iter().map(|x| test_key(group, x)).collect();
+            let mut index = 0;
+            group.tests.retain(|_| {
+                index += 1;
+                failed_keys.contains(&keys[index - 1])
+            });
+
+            for test in group.tests.iter_mut() {
+                test.retries = attempt;
+            }
+        }
+
+        merge_groups(&mut groups, retried);
+    }
+
+    Ok(TestOutcome::Tests(groups))
+}
+
+/// Builds and runs the tests once with the prepared args, see [run].
+fn run_tests(cfg: &Config, forward_args: &[String]) -> Result<TestOutcome, RunError> {
+    let runner = runner(&cfg.runner);
+    let runner_args = runner.args(forward_args, cfg);
+
+    let build = build("test", &runner.build_args(forward_args))?;
+
+    if !build.success {
+        return Ok(TestOutcome::BuildFailed(build.errors));
+    }
+
+    let watchdog = if runner.supports_timeouts() {
+        // warnings would be printed over the UI, the slow tests are shown in it instead
+        Watchdog::new(
+            cfg.timeout,
+            cfg.hard_timeout,
+            cfg.display_type != DisplayType::Json && !cfg.tui,
+            &build,
+            forward_args,
+        )
+    } else {
+        None
+    };
+    let (output, invalid_lines) = cargo_test_combined(
+        runner.command(),
+        &runner_args,
+        watchdog,
+        cfg.backtrace.as_ref(),
+    )?;
+
+    match runner.parse(output, &build, cfg.clone()) {
+        Ok(TestOutcome::Tests(mut groups)) => {
+            attach_raw_output(&mut groups, &invalid_lines);
+            Ok(TestOutcome::Tests(groups))
+        }
+        Ok(res) => Ok(res),
+        Err(err) => Err(err.to_run_error()),
+    }
+}
+
+/// Replaces the results of tests with the results from a rerun of some of them, tests that weren't in groups are added.
+/// The summaries of the groups are recounted from their tests.
+pub(crate) fn merge_groups(groups: &mut Vec<ParsedTestGroup>, rerun: Vec<ParsedTestGroup>) {
+    for group in rerun {
+        let index = match groups
+            .iter()
+            .position(|x| target_label(x) == target_label(&group))
+        {
+            Some(res) => res,
+            None => {
+                groups.push(group);
+                continue;
+            }
+        };
+
+        let existing = &mut groups[index];
+        for test in group.tests.iter() {
+            let key = test_key(&group, test);
+
+            match existing.tests.iter().position(|x| test_key(&group, x) == key) {
+                Some(res) => existing.tests[res] = test.clone(),
+                None => existing.tests.push(test.clone()),
+            }
+        }
+
+        // a crash in the first run is kept unless the tests that were rerun crashed as well
+        if group.crashed.is_some() {
+            existing.crashed = group.crashed.clone();
+        }
+
+        if let Some(summary) = existing.summary.as_mut() {
+            summary.recount(&existing.tests);
+        }
+    }
+}
+
+/// Lists the tests using `cargo test -- --list` without running them, the tests are parsed into [ParsedTestGroup]s in the same way as [run].
+/// `cargo test -- --list --ignored` is also run so that ignored tests can be marked as ignored.
+/// cmd_args works in the same way as [run].
+pub fn list(cfg: Option<Config>, cmd_args: Option<Vec<String>>) -> Result<TestOutcome, RunError> {
+    let (cfg, mut forward_args) = prepare(cfg, cmd_args)?;
+
+    let build = build("test", &forward_args)?;
+
+    if !build.success {
+        return Ok(TestOutcome::BuildFailed(build.errors));
+    }
+
+    if !forward_args.contains(&"--".to_string()) {
+        forward_args.push("--".to_string());
+    }
+    forward_args.push("--list".to_string());
+
+    let (stdout, stderr) = cargo_test("test", &forward_args)?;
+
+    forward_args.push("--ignored".to_string());
+
+    let (ignored_stdout, _) = cargo_test("test", &forward_args)?;
+
+    match parse_list(stdout, ignored_stdout, stderr, Some(&build), cfg) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(err.to_run_error()),
+    }
+}
+
+/// Runs the benchmarks using `cargo bench`, the results are parsed in the same way as [run] with each benchmark having the status [crate::parse::Status::Measured].
+/// If [Config::baseline] is set then the results are compared against the saved baseline, if [Config::save_baseline] is set then the results are saved for later runs to compare against.
+/// Baselines are saved in `target/ptest/bench`.
+/// cmd_args works in the same way as [run].
+pub fn bench(cfg: Option<Config>, cmd_args: Option<Vec<String>>) -> Result<TestOutcome, RunError> {
+    let (cfg, forward_args) = prepare(cfg, cmd_args)?;
+
+    let baseline = cfg.baseline.clone();
+    let save_as = cfg.save_baseline.clone();
+
+    let build = build("bench", &forward_args)?;
+
+    if !build.success {
+        return Ok(TestOutcome::BuildFailed(build.errors));
+    }
+
+    let watchdog = Watchdog::new(
+        cfg.timeout,
+        cfg.hard_timeout,
+        cfg.display_type != DisplayType::Json,
+        &build,
+        &forward_args,
+    );
+    let (output, _) =
+        cargo_test_combined("bench", &forward_args, watchdog, cfg.backtrace.as_ref())?;
+
+    let mut groups = match parse_with_build(output, Some(&build), cfg) {
+        Ok(TestOutcome::Tests(res)) => res,
+        Ok(res) => return Ok(res),
         Err(err) => return Err(err.to_run_error()),
     };
 
-    Ok(parsed)
+    if baseline.is_none() && save_as.is_none() {
+        return Ok(TestOutcome::Tests(groups));
+    }
+
+    // the same target directory as the history, see crate::history
+    let target_dir = match metadata_target_dir(&forward_args) {
+        Some(res) => res,
+        None => {
+            return Err(RunError::Baseline(
+                "could not find the target directory to store baselines in".to_string(),
+            ));
+        }
+    };
+
+    if let Some(name) = baseline
+        && let Err(err) = compare_baseline(&mut groups, &baseline_path(&target_dir, &name))
+    {
+        return Err(RunError::Baseline(err));
+    }
+
+    if let Some(name) = save_as
+        && let Err(err) = save_baseline(&groups, &baseline_path(&target_dir, &name))
+    {
+        return Err(RunError::Baseline(err));
+    }
+
+    Ok(TestOutcome::Tests(groups))
+}
+
+/// Parses the output of a previous `cargo test` run rather than running the tests, e.g. `cargo test 2>&1 | cargo ptest report`.
+/// The output is read from [Config::report_file], or stdin if it isn't set or is `-`. Colour codes in the output are removed before it is parsed.
+/// The output of `cargo nextest run` can be read by setting [Config::runner] to [RunnerType::Nextest].
+/// The output of a test binary that was run directly is read as a single unnamed group, [ParseError::NoTests] is returned if no tests are found.
+pub fn report(cfg: Config) -> Result<TestOutcome, RunError> {
+    let output = match cfg.report_file.as_deref() {
+        Some(path) if path != "-" => match std::fs::read(path) {
+            Ok(res) => res,
+            Err(e) => {
+                return Err(RunError::Io {
+                    context: format!("could not read {}", path),
+                    source: e,
+                });
+            }
+        },
+        _ => {
+            let mut buffer: Vec<u8> = Vec::new();
+            if let Err(e) = std::io::stdin().read_to_end(&mut buffer) {
+                return Err(RunError::Io {
+                    context: "could not read stdin".to_string(),
+                    source: e,
+                });
+            }
+            buffer
+        }
+    };
+
+    // the colour is removed before decoding so the raw bytes of invalid lines line up with the decoded text
+    let output = regex::bytes::Regex::new(r"\x1b\[[0-9;]*m")
+        .unwrap()
+        .replace_all(&output, b"")
+        .to_vec();
+    let (output, invalid_lines) = decode_output(&output);
+
+    let parsed = match cfg.runner {
+        RunnerType::Cargo => parse_combined(output, cfg),
+        RunnerType::Nextest => parse_nextest(output, None, cfg),
+    };
+
+    match parsed {
+        Ok(TestOutcome::Tests(groups)) if groups.is_empty() => {
+            Err(RunError::Parse(ParseError::NoTests))
+        }
+        Ok(TestOutcome::Tests(mut groups)) => {
+            attach_raw_output(&mut groups, &invalid_lines);
+            Ok(TestOutcome::Tests(groups))
+        }
+        Ok(res) => Ok(res),
+        Err(err) => Err(err.to_run_error()),
+    }
 }
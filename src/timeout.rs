@@ -0,0 +1,474 @@
+use crate::build::BuildInfo;
+use crate::logger::warning;
+use regex::Regex;
+use std::collections::HashSet;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Watches the output of cargo test as it is streamed and reports tests that run for longer than the timeouts.
+///
+/// libtest only prints the name of a test once it has finished, so the tests that are still running are worked out from the test binary's `--list` output.
+/// libtest starts the tests in the order they are listed, one for each thread when the binary starts and then the next one each time a test finishes,
+/// so a test started when the binary did or when the test that freed up its thread finished.
+pub(crate) struct Watchdog<'a> {
+    /// Tests that run for longer than this are reported but left running.
+    timeout: Option<Duration>,
+    /// Tests that run for longer than this are marked as timed out and the test binary is killed.
+    hard_timeout: Option<Duration>,
+    /// Print a warning when a test exceeds a timeout, disabled when the output is JSON.
+    warn: bool,
+    build: &'a BuildInfo,
+    /// The args passed to the test binaries, used to list the tests that will be run.
+    test_args: Vec<String>,
+    /// The number of tests each test binary runs at the same time.
+    threads: usize,
+    /// When the running test binary started.
+    started: Instant,
+    /// The path of the test binary that is running, None for Doc-tests as the binaries are built by rustdoc.
+    executable: Option<String>,
+    /// The tests that the running test binary will run, listed when a timeout is first exceeded.
+    tests: Option<Vec<String>>,
+    finished: HashSet<String>,
+    /// The tests that have finished in the order they finished and when, see [Watchdog::running].
+    finish_times: Vec<(String, Instant)>,
+    /// A test that has printed its name but not its status and when it printed it, e.g. when running with --nocapture.
+    unfinished: Option<(String, Instant)>,
+    /// The tests that have already been reported as slow.
+    reported: HashSet<String>,
+    /// Matches the `Running ...` line of a test binary, see [Watchdog::line].
+    executable_line: Regex,
+    /// Matches a test line with or without its status.
+    test_line: Regex,
+    /// Matches the status of a test at the end of a line.
+    status: Regex,
+}
+
+impl<'a> Watchdog<'a> {
+    /// Returns None if neither timeout is set.
+    /// forward_args are the args passed to cargo test, the args after `--` are passed to the test binaries when listing the tests.
+    pub(crate) fn new(
+        timeout: Option<u64>,
+        hard_timeout: Option<u64>,
+        warn: bool,
+        build: &'a BuildInfo,
+        forward_args: &[String],
+    ) -> Option<Watchdog<'a>> {
+        if timeout.is_none() && hard_timeout.is_none() {
+            return None;
+        }
+
+        let test_args: Vec<String> = forward_args
+            .iter()
+            .skip_while(|x| *x != "--")
+            .skip(1)
+            .cloned()
+            .collect();
+
+        Some(Watchdog {
+            timeout: timeout.map(Duration::from_secs),
+            hard_timeout: hard_timeout.map(Duration::from_secs),
+            warn,
+            build,
+            threads: test_threads(&test_args),
+            test_args,
+            started: Instant::now(),
+            executable: None,
+            tests: None,
+            finished: HashSet::new(),
+            finish_times: Vec::new(),
+            unfinished: None,
+            reported: HashSet::new(),
+            executable_line: Regex::new(r"^Running .*\((?<executable>[^()]+)\)$").unwrap(),
+            // the names of benchmarks are padded so they line up
+            test_line: Regex::new(r"^test (?<name>.+?)( - .+)?\s+\.\.\.( (?<status>.*))?$")
+                .unwrap(),
+            status: Regex::new(r"(ok|FAILED|ignored)$").unwrap(),
+        })
+    }
+
+    /// Updates which tests are running from a line of the output.
+    pub(crate) fn line(&mut self, line: &str) {
+        let line = line.trim();
+
+        if let Some(capture) = self.executable_line.captures(line) {
+            let executable = capture["executable"].to_string();
+            self.start(Some(executable));
+        } else if line.starts_with("Doc-tests ") {
+            self.start(None);
+        } else if let Some(capture) = self.test_line.captures(line) {
+            let name = capture["name"].to_string();
+            let finished = capture.name("status").is_some_and(|x| {
+                x.as_str().starts_with("bench:") || self.status.is_match(x.as_str())
+            });
+
+            if finished {
+                self.finish(name);
+            } else {
+                self.unfinished = Some((name, Instant::now()));
+            }
+        } else if self.status.is_match(line)
+            && let Some((name, _)) = self.unfinished.take()
+        {
+            self.finish(name);
+        }
+    }
+
+    fn start(&mut self, executable: Option<String>) {
+        self.started = Instant::now();
+        self.executable = executable;
+        self.tests = None;
+        self.finished.clear();
+        self.finish_times.clear();
+        self.unfinished = None;
+        self.reported.clear();
+    }
+
+    fn finish(&mut self, name: String) {
+        self.finish_times.push((name.clone(), Instant::now()));
+        self.finished.insert(name);
+    }
+
+    /// Checks if the tests that are running have exceeded either timeout.
+    /// Returns the lines to add to the output and whether the test binary should be killed.
+    /// The lines are `test name has been running for over N seconds`, which is the same as libtest's warning, and `test name timed out after N seconds`.
+    pub(crate) fn check(&mut self) -> (Vec<String>, bool) {
+        let mut lines: Vec<String> = Vec::new();
+
+        // no test can have run for longer than the binary, which saves listing the tests of binaries that finish quickly
+        let shortest = self.timeout.into_iter().chain(self.hard_timeout).min();
+        if shortest.is_none_or(|x| self.started.elapsed() <= x) {
+            return (lines, false);
+        }
+
+        let running = self.running();
+
+        if let Some(hard_timeout) = self.hard_timeout {
+            let (timed_out, others): (Vec<_>, Vec<_>) =
+                running.iter().partition(|x| x.1.elapsed() > hard_timeout);
+
+            if !timed_out.is_empty() {
+                for (name, _) in &timed_out {
+                    lines.push(format!(
+                        "test {} timed out after {} seconds",
+                        name,
+                        hard_timeout.as_secs()
+                    ));
+                }
+
+                if self.warn {
+                    let names = |tests: &[&(String, Instant)]| {
+                        tests
+                            .iter()
+                            .map(|x| x.0.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    };
+                    warning!(
+                        "killing the test binary as {} ran for over {} seconds",
+                        names(&timed_out),
+                        hard_timeout.as_secs()
+                    );
+                    if !others.is_empty() {
+                        warning!("these tests were also stopped: {}", names(&others));
+                    }
+                }
+
+                // the next test binary is started by cargo after this one is killed
+                self.start(None);
+                return (lines, true);
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            for (name, started) in running {
+                if started.elapsed() > timeout && self.reported.insert(name.clone()) {
+                    if self.warn {
+                        warning!(
+                            "{} has been running for over {} seconds",
+                            name,
+                            timeout.as_secs()
+                        );
+                    }
+                    lines.push(format!(
+                        "test {} has been running for over {} seconds",
+                        name,
+                        timeout.as_secs()
+                    ));
+                }
+            }
+        }
+
+        (lines, false)
+    }
+
+    /// The tests the running test binary hasn't finished yet and when they started, limited to the number of tests that run at the same time.
+    /// libtest starts the tests in the order they are listed so the first unfinished tests are the ones that are running,
+    /// the first test on each thread starts with the binary and the one after that when the first test to finish does, and so on.
+    fn running(&mut self) -> Vec<(String, Instant)> {
+        if self.tests.is_none() {
+            self.tests = Some(self.list_tests());
+        }
+        let tests = self.tests.as_deref().unwrap_or_default();
+
+        // ignored tests don't use a thread so they don't start another test when they finish
+        let listed: HashSet<&String> = tests.iter().collect();
+        let finish_times: Vec<Instant> = self
+            .finish_times
+            .iter()
+            .filter(|x| listed.contains(&x.0))
+            .map(|x| x.1)
+            .collect();
+
+        let mut running: Vec<(String, Instant)> = tests
+            .iter()
+            .enumerate()
+            .filter(|x| !self.finished.contains(x.1))
+            .take(self.threads)
+            .map(|(index, name)| {
+                // the last finish is used if the list doesn't match the tests that were run
+                let started = match index.checked_sub(self.threads) {
+                    Some(res) => finish_times.get(res).or(finish_times.last()).copied(),
+                    None => None,
+                };
+                (name.clone(), started.unwrap_or(self.started))
+            })
+            .collect();
+
+        if let Some((name, started)) = &self.unfinished {
+            match running.iter_mut().find(|x| &x.0 == name) {
+                Some(res) => res.1 = *started,
+                None => running.insert(0, (name.clone(), *started)),
+            }
+        }
+
+        running
+    }
+
+    /// Kills the test binary that cargo is running, cargo then reports it as failed and moves on to the next one if --no-fail-fast was passed.
+    /// On unix only the children of cargo are killed with pkill, on other platforms or if pkill fails cargo itself has to be killed so the rest of the tests won't run.
+    /// Returns true if cargo was killed, the test binary can outlive it and keep writing to the output then.
+    pub(crate) fn kill_test_binary(&self, cargo: &mut Child) -> bool {
+        #[cfg(unix)]
+        {
+            let killed = Command::new("pkill")
+                .args(["-KILL", "-P", cargo.id().to_string().as_str()])
+                .stderr(Stdio::null())
+                .status();
+
+            match killed {
+                // 1 means the test binary had already exited
+                Ok(res) if res.success() || res.code() == Some(1) => return false,
+                Ok(res) if self.warn => {
+                    warning!("pkill failed ({}), killing cargo instead", res)
+                }
+                Err(e) if self.warn => {
+                    warning!("pkill couldn't be run ({}), killing cargo instead", e)
+                }
+                _ => {}
+            }
+        }
+
+        let _ = cargo.kill();
+        true
+    }
+
+    /// Lists the tests that the running test binary will run, ignored tests are left out unless they were asked for.
+    fn list_tests(&self) -> Vec<String> {
+        let executable = match self
+            .executable
+            .as_ref()
+            .and_then(|x| self.build.artifact(x))
+        {
+            Some(res) => res.executable.clone(),
+            None => return Vec::new(),
+        };
+
+        let list = |extra_args: &[&str]| -> Vec<String> {
+            let output = Command::new(&executable)
+                .args(&self.test_args)
+                .arg("--list")
+                .args(extra_args)
+                .stderr(Stdio::null())
+                .output();
+
+            match output {
+                Ok(res) => String::from_utf8_lossy(&res.stdout)
+                    .lines()
+                    .filter_map(|x| x.strip_suffix(": test"))
+                    .map(|x| x.to_string())
+                    .collect(),
+                Err(_) => Vec::new(),
+            }
+        };
+
+        let tests = list(&[]);
+
+        if self
+            .test_args
+            .iter()
+            .any(|x| x == "--ignored" || x == "--include-ignored")
+        {
+            return tests;
+        }
+
+        let ignored = list(&["--ignored"]);
+        tests.into_iter().filter(|x| !ignored.contains(x)).collect()
+    }
+}
+
+/// Gets the number of tests each test binary runs at the same time from --test-threads, RUST_TEST_THREADS or the number of cpus.
+fn test_threads(test_args: &[String]) -> usize {
+    let from_args = test_args.iter().enumerate().find_map(|(index, x)| {
+        match x.strip_prefix("--test-threads") {
+            Some(value) if value.starts_with('=') => value[1..].parse::<usize>().ok(),
+            Some("") => test_args.get(index + 1)?.parse::<usize>().ok(),
+            _ => None,
+        }
+    });
+
+    from_args
+        .or_else(|| {
+            std::env::var("RUST_TEST_THREADS")
+                .ok()?
+                .parse::<usize>()
+                .ok()
+        })
+        .or_else(|| std::thread::available_parallelism().ok().map(|x| x.get()))
+        .unwrap_or(1)
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_threads, Watchdog};
+    use crate::build::BuildInfo;
+    use std::time::{Duration, Instant};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    /// Lets the watchdog see no progress for longer than a timeout of 0 seconds.
+    fn stall() {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    #[test]
+    fn reports_unfinished_tests_once() {
+        let build = BuildInfo::default();
+        let mut watchdog = Watchdog::new(Some(0), None, false, &build, &[]).unwrap();
+
+        watchdog
+            .line("     Running unittests src/lib.rs (target/debug/deps/slow-0123456789abcdef)");
+        watchdog.line("test tests::fast ... ok");
+        watchdog.line("test tests::slow ... ");
+        stall();
+
+        assert_eq!(
+            watchdog.check(),
+            (
+                vec!["test tests::slow has been running for over 0 seconds".to_string()],
+                false
+            )
+        );
+
+        // a test is only reported once
+        stall();
+        assert_eq!(watchdog.check(), (Vec::new(), false));
+
+        // output from the test doesn't finish it, its status does
+        watchdog.line("printed by the test");
+        assert_eq!(
+            watchdog.unfinished.as_ref().map(|x| x.0.as_str()),
+            Some("tests::slow")
+        );
+        watchdog.line("ok");
+        assert!(watchdog.unfinished.is_none());
+        assert!(watchdog.finished.contains("tests::fast"));
+        assert!(watchdog.finished.contains("tests::slow"));
+
+        // a new test binary starts with nothing finished
+        watchdog.line("   Doc-tests slow");
+        assert!(watchdog.finished.is_empty());
+    }
+
+    #[test]
+    fn times_each_test_from_when_it_started() {
+        let build = BuildInfo::default();
+        let forward_args = args(&["--", "--test-threads=2"]);
+        let mut watchdog = Watchdog::new(Some(3), Some(10), false, &build, &forward_args).unwrap();
+        let ago = |seconds: u64| Instant::now() - Duration::from_secs(seconds);
+
+        // tests::a is stuck while the tests on the other thread keep finishing
+        watchdog.tests = Some(args(&["tests::a", "tests::b", "tests::c", "tests::d"]));
+        watchdog.started = ago(5);
+        for (name, seconds) in [("tests::b", 4), ("tests::ignored", 3), ("tests::c", 1)] {
+            watchdog.finished.insert(name.to_string());
+            watchdog.finish_times.push((name.to_string(), ago(seconds)));
+        }
+
+        // tests::d started when tests::c finished, the ignored test didn't free up a thread
+        let running: Vec<String> = watchdog.running().into_iter().map(|x| x.0).collect();
+        assert_eq!(running, args(&["tests::a", "tests::d"]));
+        assert_eq!(
+            watchdog.check(),
+            (
+                vec!["test tests::a has been running for over 3 seconds".to_string()],
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn kills_the_binary_after_the_hard_timeout() {
+        let build = BuildInfo::default();
+        let mut watchdog = Watchdog::new(Some(0), Some(0), false, &build, &[]).unwrap();
+
+        watchdog.line("test tests::hangs - should panic ... ");
+        stall();
+
+        assert_eq!(
+            watchdog.check(),
+            (
+                vec!["test tests::hangs timed out after 0 seconds".to_string()],
+                true
+            )
+        );
+        assert!(watchdog.unfinished.is_none());
+    }
+
+    #[test]
+    fn finishes_benchmarks() {
+        let build = BuildInfo::default();
+        let mut watchdog = Watchdog::new(Some(0), None, false, &build, &[]).unwrap();
+
+        watchdog.line("test benches::sum   ... bench:           0.39 ns/iter (+/- 0.14)");
+        stall();
+
+        assert!(watchdog.finished.contains("benches::sum"));
+        assert_eq!(watchdog.check(), (Vec::new(), false));
+    }
+
+    #[test]
+    fn needs_a_timeout() {
+        let build = BuildInfo::default();
+        assert!(Watchdog::new(None, None, false, &build, &[]).is_none());
+    }
+
+    #[test]
+    fn reads_test_threads() {
+        assert_eq!(test_threads(&args(&["--test-threads=3"])), 3);
+        assert_eq!(
+            test_threads(&args(&["--nocapture", "--test-threads", "2"])),
+            2
+        );
+        assert_eq!(test_threads(&args(&["--test-threads=0"])), 1);
+        assert!(test_threads(&args(&["--test-threads=x"])) >= 1);
+
+        let build = BuildInfo::default();
+        let forward_args = args(&["--lib", "--", "--test-threads=4"]);
+        let watchdog = Watchdog::new(Some(1), None, false, &build, &forward_args).unwrap();
+        assert_eq!(watchdog.threads, 4);
+        assert_eq!(watchdog.test_args, args(&["--test-threads=4"]));
+    }
+}
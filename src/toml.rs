@@ -0,0 +1,499 @@
+use crate::json::JsonValue;
+
+/// Parses the subset of TOML used by `ptest.toml` into a [JsonValue::Object] so it can be read in the same way as the metadata from `cargo metadata`.
+/// Supports tables, dotted keys, strings, integers, floats, booleans, arrays and inline tables on one line.
+/// Arrays of tables, multi-line strings, dates and times, and hexadecimal, octal and binary integers aren't supported.
+/// Returns an error with the line number if the document can't be parsed, which names the construct if it isn't supported.
+pub(crate) fn parse(input: &str) -> Result<JsonValue, String> {
+    let mut parser = TomlParser {
+        chars: input.chars().peekable(),
+        line: 1,
+    };
+
+    match parser.document() {
+        Ok(res) => Ok(res),
+        Err(err) => Err(format!("line {}: {}", parser.line, err)),
+    }
+}
+
+/// Gets the table at path, creating any tables that don't exist yet.
+fn table<'a>(
+    root: &'a mut Vec<(String, JsonValue)>,
+    path: &[String],
+) -> Result<&'a mut Vec<(String, JsonValue)>, String> {
+    let (first, rest) = match path.split_first() {
+        Some(res) => res,
+        None => return Ok(root),
+    };
+
+    let index = match root.iter().position(|x| &x.0 == first) {
+        Some(res) => res,
+        None => {
+            root.push((first.clone(), JsonValue::Object(Vec::new())));
+            root.len() - 1
+        }
+    };
+
+    match &mut root[index].1 {
+        JsonValue::Object(entries) => table(entries, rest),
+        _ => Err(format!("{} is not a table", first)),
+    }
+}
+
+struct TomlParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    /// The line being parsed, used in errors.
+    line: usize,
+}
+
+impl TomlParser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// Skips spaces and tabs but not new lines.
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|x| *x == ' ' || *x == '\t').is_some() {}
+    }
+
+    /// Skips whitespace, new lines and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.next();
+                }
+                Some('#') => {
+                    while self.chars.peek().is_some_and(|x| *x != '\n') {
+                        self.next();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Checks that nothing but a comment is left on the line.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+
+        match self.chars.peek() {
+            None | Some('\n' | '\r' | '#') => Ok(()),
+            Some(c) => Err(format!("expected the end of the line but got '{}'", c)),
+        }
+    }
+
+    fn document(&mut self) -> Result<JsonValue, String> {
+        let mut root: Vec<(String, JsonValue)> = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_blank();
+
+            match self.chars.peek() {
+                None => return Ok(JsonValue::Object(root)),
+                Some('[') => {
+                    self.next();
+                    if self.chars.peek() == Some(&'[') {
+                        return Err("arrays of tables are not supported".to_string());
+                    }
+
+                    self.skip_spaces();
+                    current = self.key()?;
+                    self.skip_spaces();
+
+                    if self.next() != Some(']') {
+                        return Err("expected ] at the end of the table header".to_string());
+                    }
+                    self.end_of_line()?;
+                    table(&mut root, &current)?;
+                }
+                Some(_) => {
+                    let (key, value) = self.key_value()?;
+                    self.end_of_line()?;
+                    insert(&mut root, &current, key, value)?;
+                }
+            }
+        }
+    }
+
+    /// Parses `key = value`, the key can be dotted, e.g. `profile.ci.retries = 2`.
+    fn key_value(&mut self) -> Result<(Vec<String>, JsonValue), String> {
+        let key = self.key()?;
+        self.skip_spaces();
+
+        if self.next() != Some('=') {
+            return Err(format!("expected = after {}", key.join(".")));
+        }
+
+        self.skip_spaces();
+        Ok((key, self.value()?))
+    }
+
+    /// Parses a key, the parts of a dotted key are returned separately.
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut parts: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_spaces();
+
+            let part = match self.chars.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .chars
+                        .next_if(|x| x.is_ascii_alphanumeric() || *x == '-' || *x == '_')
+                    {
+                        part.push(c);
+                    }
+                    part
+                }
+            };
+
+            if part.is_empty() {
+                return Err("expected a key".to_string());
+            }
+            parts.push(part);
+
+            self.skip_spaces();
+            if self.chars.next_if_eq(&'.').is_none() {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        let start: String = self.chars.clone().take(3).collect();
+        if start == "\"\"\"" || start == "'''" {
+            return Err("multi-line strings are not supported".to_string());
+        }
+
+        match self.chars.peek() {
+            Some('"') => self.basic_string().map(JsonValue::String),
+            Some('\'') => self.literal_string().map(JsonValue::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|x| x.is_ascii_alphanumeric() || ['-', '+', '.', '_'].contains(x))
+                {
+                    word.push(c);
+                }
+
+                // e.g. 1979-05-27, 1979-05-27T07:32:00Z or 07:32:00
+                let bytes = word.as_bytes();
+                let is_date = bytes.len() >= 10
+                    && bytes[4] == b'-'
+                    && bytes[7] == b'-'
+                    && bytes[..4].iter().all(|x| x.is_ascii_digit());
+                let is_time = self.chars.peek() == Some(&':');
+
+                match word.as_str() {
+                    "true" => Ok(JsonValue::Bool(true)),
+                    "false" => Ok(JsonValue::Bool(false)),
+                    _ if is_date || is_time => Err(format!(
+                        "dates and times are not supported, use a string instead of {}",
+                        word
+                    )),
+                    _ if ["0x", "0o", "0b"].iter().any(|x| word.starts_with(x)) => Err(format!(
+                        "hexadecimal, octal and binary integers are not supported, use a decimal integer instead of {}",
+                        word
+                    )),
+                    _ => match word.replace('_', "").parse::<f64>() {
+                        Ok(res) => Ok(JsonValue::Number(res)),
+                        Err(_) => Err(format!("invalid value {}", word)),
+                    },
+                }
+            }
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.next();
+        let mut value = String::new();
+
+        loop {
+            // the new line isn't taken so the error is reported on the line of the string
+            match self.chars.next_if(|x| *x != '\n') {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some(c) => return Err(format!("unsupported escape \\{}", c)),
+                    None => return Err("unterminated string".to_string()),
+                },
+                Some(c) => value.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.next();
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next_if(|x| *x != '\n') {
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    /// Arrays can be split over several lines and have a trailing comma.
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.next();
+        let mut values: Vec<JsonValue> = Vec::new();
+
+        loop {
+            self.skip_blank();
+
+            if self.chars.next_if_eq(&']').is_some() {
+                return Ok(JsonValue::Array(values));
+            }
+
+            values.push(self.value()?);
+            self.skip_blank();
+
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(values)),
+                _ => return Err("expected , or ] in array".to_string()),
+            }
+        }
+    }
+
+    /// Inline tables have to be on one line, e.g. `reporters = { json = "report.json" }`.
+    fn inline_table(&mut self) -> Result<JsonValue, String> {
+        self.next();
+        let mut entries: Vec<(String, JsonValue)> = Vec::new();
+
+        self.skip_spaces();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(JsonValue::Object(entries));
+        }
+
+        loop {
+            self.skip_spaces();
+            if matches!(self.chars.peek(), Some('\n' | '\r')) {
+                return Err("inline tables have to be on one line".to_string());
+            }
+
+            let (key, value) = self.key_value()?;
+            insert(&mut entries, &[], key, value)?;
+            self.skip_spaces();
+
+            if matches!(self.chars.peek(), Some('\n' | '\r')) {
+                return Err("inline tables have to be on one line".to_string());
+            }
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(entries)),
+                _ => return Err("expected , or } in inline table".to_string()),
+            }
+        }
+    }
+}
+
+/// Inserts a value into the table at path, a dotted key creates the tables it goes through.
+fn insert(
+    root: &mut Vec<(String, JsonValue)>,
+    path: &[String],
+    key: Vec<String>,
+    value: JsonValue,
+) -> Result<(), String> {
+    let mut full_path = path.to_vec();
+    full_path.extend(key);
+
+    let name = match full_path.pop() {
+        Some(res) => res,
+        None => return Err("expected a key".to_string()),
+    };
+    let entries = table(root, &full_path)?;
+
+    if entries.iter().any(|x| x.0 == name) {
+        return Err(format!("{} is defined more than once", name));
+    }
+
+    entries.push((name, value));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::json::JsonValue;
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    fn object(entries: &[(&str, JsonValue)]) -> JsonValue {
+        JsonValue::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn skips_comments() {
+        let input = "# ptest.toml\nformat = \"tree\" # the display\n\n  # indented\nretries = 2\n";
+
+        assert_eq!(
+            parse(input),
+            Ok(object(&[
+                ("format", string("tree")),
+                ("retries", JsonValue::Number(2.0)),
+            ]))
+        );
+        // a # in a string isn't a comment
+        assert_eq!(
+            parse("output = 'target/#1.txt'"),
+            Ok(object(&[("output", string("target/#1.txt"))]))
+        );
+    }
+
+    #[test]
+    fn parses_multi_line_arrays() {
+        let input =
+            "args = [\n    \"--workspace\", # every package\n    '--no-fail-fast',\n]\nempty = [ ]";
+
+        assert_eq!(
+            parse(input),
+            Ok(object(&[
+                (
+                    "args",
+                    JsonValue::Array(vec![string("--workspace"), string("--no-fail-fast")])
+                ),
+                ("empty", JsonValue::Array(Vec::new())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parses_inline_tables() {
+        let input = "reporters = { json = \"report.json\", junit.path = 'junit.xml' }\nnone = {}";
+
+        assert_eq!(
+            parse(input),
+            Ok(object(&[
+                (
+                    "reporters",
+                    object(&[
+                        ("json", string("report.json")),
+                        ("junit", object(&[("path", string("junit.xml"))])),
+                    ])
+                ),
+                ("none", object(&[])),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parses_dotted_keys_and_tables() {
+        let input = "profile.ci.retries = 2\n\n[profile.ci]\nshow-output = true\n\n[profile.\"local dev\"]\ntimeout = 1.5";
+
+        assert_eq!(
+            parse(input),
+            Ok(object(&[(
+                "profile",
+                object(&[
+                    (
+                        "ci",
+                        object(&[
+                            ("retries", JsonValue::Number(2.0)),
+                            ("show-output", JsonValue::Bool(true)),
+                        ])
+                    ),
+                    ("local dev", object(&[("timeout", JsonValue::Number(1.5))])),
+                ])
+            )]))
+        );
+        assert_eq!(
+            parse("a.b = 1\na = 2"),
+            Err("line 2: a is defined more than once".to_string())
+        );
+        assert_eq!(
+            parse("a = 1\na.b = 2"),
+            Err("line 2: a is not a table".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let error = |input: &str| parse(input).unwrap_err();
+
+        assert_eq!(error("format = tree"), "line 1: invalid value tree");
+        assert_eq!(
+            error("# comment\n\nformat = \"tree\" json"),
+            "line 3: expected the end of the line but got 'j'"
+        );
+        assert_eq!(
+            error("args = [\n    \"--workspace\"\n    \"--lib\"\n]"),
+            "line 3: expected , or ] in array"
+        );
+        assert_eq!(
+            error("a = 1\nname = \"open\nb = 2"),
+            "line 2: unterminated string"
+        );
+        assert_eq!(error("name = 'open\n"), "line 1: unterminated string");
+    }
+
+    #[test]
+    fn names_unsupported_constructs() {
+        let error = |input: &str| parse(input).unwrap_err();
+
+        assert_eq!(
+            error("\n[[reporters]]"),
+            "line 2: arrays of tables are not supported"
+        );
+        assert_eq!(
+            error("a = { b = 1,\nc = 2 }"),
+            "line 1: inline tables have to be on one line"
+        );
+        assert_eq!(
+            error("a = { b = 1\n}"),
+            "line 1: inline tables have to be on one line"
+        );
+        assert_eq!(
+            error("a = \"\"\"\ntext\"\"\""),
+            "line 1: multi-line strings are not supported"
+        );
+        assert_eq!(
+            error("a = '''text'''"),
+            "line 1: multi-line strings are not supported"
+        );
+        assert_eq!(
+            error("since = 1979-05-27T07:32:00Z"),
+            "line 1: dates and times are not supported, use a string instead of 1979-05-27T07"
+        );
+        assert_eq!(
+            error("at = 07:32:00"),
+            "line 1: dates and times are not supported, use a string instead of 07"
+        );
+        assert_eq!(
+            error("mask = 0xff"),
+            "line 1: hexadecimal, octal and binary integers are not supported, use a decimal integer instead of 0xff"
+        );
+        assert_eq!(
+            error("name = \"\\u00e9\""),
+            "line 1: unsupported escape \\u"
+        );
+        assert_eq!(parse("a = \"\""), Ok(object(&[("a", string(""))])));
+    }
+}
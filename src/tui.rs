@@ -0,0 +1,720 @@
+use crate::config::{BacktraceStyle, Config};
+use crate::display::{reason_lines, target_label, Colourise, Display};
+use crate::parse::{ParsedTest, ParsedTestGroup, Status, TestOutcome};
+use crate::run::{merge_groups, run, RunError};
+use crate::watch::{failed_tests, filter_args, Filters};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when ctrl+c is pressed or ptest is told to stop, the UI is closed the next time it checks so the terminal can be restored.
+static STOPPED: AtomicBool = AtomicBool::new(false);
+/// Set when the terminal is resized, the size is only looked up again after this.
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+/// Catches SIGINT, SIGTERM and SIGWINCH with `signal` from the C library so the rest of ptest doesn't need a dependency for it.
+/// The handler only sets [STOPPED] or [RESIZED], the terminal is restored by the main loop as little is safe to do in a signal handler.
+#[cfg(unix)]
+fn handle_signals() {
+    const SIGINT: i32 = 2;
+    const SIGTERM: i32 = 15;
+    // the same on linux and macOS
+    const SIGWINCH: i32 = 28;
+
+    unsafe extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn on_signal(signum: i32) {
+        match signum {
+            SIGWINCH => RESIZED.store(true, Ordering::SeqCst),
+            _ => STOPPED.store(true, Ordering::SeqCst),
+        }
+    }
+
+    // SAFETY: the handler only stores to atomics, which is async-signal-safe
+    unsafe {
+        signal(SIGINT, on_signal);
+        signal(SIGTERM, on_signal);
+        signal(SIGWINCH, on_signal);
+    }
+}
+
+/// Signals aren't caught on other platforms, which don't have stty anyway.
+#[cfg(not(unix))]
+fn handle_signals() {}
+
+/// Runs stty on the terminal, stdin has to be inherited so that stty changes the terminal rather than a pipe.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|x| x.trim().to_string())
+}
+
+/// Shows the cursor, leaves the alternate screen and restores the terminal settings saved with `stty -g`.
+fn restore_terminal(saved: &str) {
+    print!("\x1b[?25h\x1b[?1049l");
+    let _ = std::io::stdout().flush();
+    stty(&[saved]);
+}
+
+/// The number of rows and columns of the terminal, 24x80 is used if stty can't find them.
+fn terminal_size() -> (usize, usize) {
+    stty(&["size"])
+        .and_then(|x| {
+            let (rows, columns) = x.split_once(' ')?;
+            Some((rows.parse().ok()?, columns.parse().ok()?))
+        })
+        .unwrap_or((24, 80))
+}
+
+/// A key pressed by the user, escape sequences for the arrow keys are combined into a single key.
+#[derive(PartialEq)]
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Enter,
+    Backspace,
+    Escape,
+}
+
+/// Reads the next key, returns None if no key was pressed within the timeout set by stty.
+fn read_key() -> Option<Key> {
+    let mut stdin = std::io::stdin().lock();
+    let mut buffer = [0u8; 1];
+
+    let mut next = || match stdin.read(&mut buffer) {
+        Ok(1) => Some(buffer[0]),
+        _ => None,
+    };
+
+    let key = match next()? {
+        b'\r' | b'\n' => Key::Enter,
+        127 | 8 => Key::Backspace,
+        27 => match next() {
+            Some(b'[') => match next() {
+                Some(b'A') => Key::Up,
+                Some(b'B') => Key::Down,
+                Some(b'C') => Key::Right,
+                Some(b'D') => Key::Left,
+                Some(b'5') => {
+                    next();
+                    Key::PageUp
+                }
+                Some(b'6') => {
+                    next();
+                    Key::PageDown
+                }
+                _ => return None,
+            },
+            _ => Key::Escape,
+        },
+        x if x.is_ascii() => Key::Char(x as char),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Shortens a string to at most width characters.
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// The short label shown before each test in the tree and the colour it is shown in.
+fn status_label(status: &Status) -> String {
+    match status {
+        Status::Passed => "Pass".green(),
+        Status::Failed => "Failed".red(),
+        Status::Ignored => "Ignored".yellow(),
+        Status::Listed => "Listed".blue(),
+        Status::Measured => "Bench".blue(),
+        Status::TimedOut => "Timed Out".red(),
+    }
+}
+
+/// A node in the tree of tests, grouped by package -> target -> file -> module like [Display::tree].
+struct Node {
+    name: String,
+    /// Identifies the node so that it stays collapsed after the tests are rerun.
+    key: String,
+    children: Vec<Node>,
+    /// The indexes of the group and test for each test directly in this node.
+    tests: Vec<(usize, usize)>,
+}
+
+impl Node {
+    fn new(name: &str, key: String) -> Self {
+        Node {
+            name: name.to_string(),
+            key,
+            children: Vec::new(),
+            tests: Vec::new(),
+        }
+    }
+
+    /// Gets the child with the given name, creating it if it doesn't exist.
+    fn child(&mut self, name: &str) -> &mut Node {
+        let index = match self.children.iter().position(|x| x.name == name) {
+            Some(res) => res,
+            None => {
+                let key = format!("{}/{}", self.key, name);
+                self.children.push(Node::new(name, key));
+                self.children.len() - 1
+            }
+        };
+
+        &mut self.children[index]
+    }
+}
+
+/// A line in the tree, either a node that can be collapsed or a test.
+struct Row {
+    depth: usize,
+    /// The key of the node, empty for tests.
+    key: String,
+    name: String,
+    test: Option<(usize, usize)>,
+}
+
+/// The state of the terminal UI, see [tui].
+struct Tui {
+    cfg: Config,
+    cmd_args: Vec<String>,
+    groups: Vec<ParsedTestGroup>,
+    /// The keys of the collapsed nodes.
+    collapsed: Vec<String>,
+    /// Only tests with this status are shown, failed also includes timed out tests.
+    filter: Option<Status>,
+    search: String,
+    /// The search is being typed.
+    searching: bool,
+    selected: usize,
+    /// The first row shown in the tree.
+    scroll: usize,
+    /// The first line shown in the detail pane.
+    detail_scroll: usize,
+    /// Shown in the status bar, e.g. the result of a rerun.
+    message: String,
+    /// The rows and columns of the terminal, looked up again when it is resized, see [RESIZED].
+    size: (usize, usize),
+}
+
+impl Tui {
+    /// Checks if a test matches the status filter and the search.
+    fn visible(&self, group: &ParsedTestGroup, test: &ParsedTest) -> bool {
+        let status_matches = match &self.filter {
+            None => true,
+            Some(Status::Failed) => {
+                test.status == Status::Failed || test.status == Status::TimedOut
+            }
+            Some(res) => test.status == *res,
+        };
+
+        let search = self.search.to_lowercase();
+        let (_, target) = target_label(group);
+        let name_matches = search.is_empty()
+            || test.module_path.to_lowercase().contains(&search)
+            || target.to_lowercase().contains(&search)
+            || test
+                .file_path
+                .as_ref()
+                .is_some_and(|x| x.to_lowercase().contains(&search));
+
+        status_matches && name_matches
+    }
+
+    /// Builds the tree from the tests that match the filter and search and flattens it into the rows that aren't inside a collapsed node.
+    fn rows(&self) -> Vec<Row> {
+        let mut root = Node::new("", "".to_string());
+
+        for (group_index, group) in self.groups.iter().enumerate() {
+            let (package, target) = target_label(group);
+
+            for (test_index, test) in group.tests.iter().enumerate() {
+                if !self.visible(group, test) {
+                    continue;
+                }
+
+                let mut node = root.child(&package).child(&target);
+
+                if let Some(file_path) = &test.file_path {
+                    node = node.child(file_path);
+                }
+
+                let mut components: Vec<&str> = test
+                    .module_path
+                    .split("::")
+                    .filter(|x| !x.is_empty())
+                    .collect();
+                components.pop();
+
+                for component in components {
+                    node = node.child(component);
+                }
+
+                node.tests.push((group_index, test_index));
+            }
+        }
+
+        let mut rows: Vec<Row> = Vec::new();
+        for child in &root.children {
+            self.add_rows(child, 0, &mut rows);
+        }
+        rows
+    }
+
+    fn add_rows(&self, node: &Node, depth: usize, rows: &mut Vec<Row>) {
+        rows.push(Row {
+            depth,
+            key: node.key.clone(),
+            name: node.name.clone(),
+            test: None,
+        });
+
+        if self.collapsed.contains(&node.key) {
+            return;
+        }
+
+        for (group_index, test_index) in &node.tests {
+            let test = &self.groups[*group_index].tests[*test_index];
+            let name = test.module_path.split("::").last().unwrap_or("");
+
+            rows.push(Row {
+                depth: depth + 1,
+                key: "".to_string(),
+                name: match test.line_number {
+                    Some(line) if name.is_empty() => format!("(line {})", line),
+                    Some(line) => format!("{} (line {})", name, line),
+                    None => name.to_string(),
+                },
+                test: Some((*group_index, *test_index)),
+            });
+        }
+
+        for child in &node.children {
+            self.add_rows(child, depth + 1, rows);
+        }
+    }
+
+    /// The index of the first row that is a test, so a test is selected after the rows change.
+    fn first_test(&self) -> usize {
+        self.rows()
+            .iter()
+            .position(|x| x.test.is_some())
+            .unwrap_or(0)
+    }
+
+    fn selected_test(&self, rows: &[Row]) -> Option<(usize, usize)> {
+        rows.get(self.selected).and_then(|x| x.test)
+    }
+
+    /// The lines shown in the detail pane for the selected row.
+    fn detail(&self, rows: &[Row]) -> Vec<String> {
+        let row = match rows.get(self.selected) {
+            Some(res) => res,
+            None => return vec!["No tests match the filter".yellow()],
+        };
+
+        let (group_index, test_index) = match row.test {
+            Some(res) => res,
+            None => {
+                return vec![format!(
+                    "{} - {}",
+                    row.name.bold(),
+                    if self.collapsed.contains(&row.key) {
+                        "collapsed, press enter to expand"
+                    } else {
+                        "press enter to collapse"
+                    }
+                )];
+            }
+        };
+
+        let group = &self.groups[group_index];
+        let test = &group.tests[test_index];
+        let (_, target) = target_label(group);
+        let mut lines: Vec<String> = vec![
+            format!(
+                "{} - {}",
+                status_label(&test.status),
+                test.module_path.bold()
+            ),
+            format!("{} {}", "in".blue(), target),
+        ];
+
+        if let Some(file_path) = &test.file_path {
+            lines.push(format!(
+                "{} {}{}",
+                "from".blue(),
+                file_path,
+                test.line_number
+                    .map_or("".to_string(), |x| format!(" (line {})", x))
+            ));
+        }
+        if let Some(doc_kind) = &test.doc_kind {
+            lines.push(format!("{} {}", "kind".blue(), doc_kind));
+        }
+        if let Some(failure_kind) = &test.failure_kind {
+            lines.push(format!("{} {}", "failure".red(), failure_kind));
+        }
+        if let Some(ignore_reason) = &test.ignore_reason {
+            lines.push(format!("{} {}", "reason".yellow(), ignore_reason));
+        }
+        if test.raw_output.is_some() {
+            lines.push(format!(
+                "{} the test printed invalid UTF-8, the invalid bytes are shown as \u{FFFD}",
+                "note".yellow()
+            ));
+        }
+
+        lines.push("".to_string());
+        match test.status {
+            // the backtrace is collapsed in the same way as the failures in the linear and tree displays
+            Status::Failed | Status::TimedOut if test.error_reason.is_some() => lines.extend(
+                reason_lines(test, self.cfg.backtrace == Some(BacktraceStyle::Full)),
+            ),
+            Status::Failed => lines.push("No failure output".yellow()),
+            Status::TimedOut => {}
+            _ => {
+                if let Some(output) = &test.output {
+                    lines.extend(output.lines().map(|x| x.to_string()));
+                }
+            }
+        }
+
+        lines
+    }
+
+    /// Draws the tree at the top, the detail pane for the selected row at the bottom and the status bar under it.
+    fn draw(&mut self) {
+        if RESIZED.swap(false, Ordering::SeqCst) {
+            self.size = terminal_size();
+        }
+        let (height, width) = self.size;
+        let rows = self.rows();
+
+        if self.selected >= rows.len() {
+            self.selected = rows.len().saturating_sub(1);
+        }
+
+        let tree_height = (height.saturating_sub(3) * 3 / 5).max(1);
+        let detail_height = height.saturating_sub(tree_height + 3);
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + tree_height {
+            self.scroll = self.selected + 1 - tree_height;
+        }
+
+        let mut frame = String::from("\x1b[H");
+        let line = |frame: &mut String, text: String| {
+            frame.push_str(&text);
+            frame.push_str("\x1b[K\r\n");
+        };
+
+        for index in self.scroll..self.scroll + tree_height {
+            let row = match rows.get(index) {
+                Some(res) => res,
+                None => {
+                    line(&mut frame, "".to_string());
+                    continue;
+                }
+            };
+
+            let indent = "  ".repeat(row.depth);
+            let text = match row.test {
+                Some((group_index, test_index)) => {
+                    let status = &self.groups[group_index].tests[test_index].status;
+                    format!(
+                        "{}{} - {}",
+                        indent,
+                        status_label(status),
+                        truncate(&row.name, width.saturating_sub(indent.len() + 12))
+                    )
+                }
+                None => format!(
+                    "{}{} {}",
+                    indent,
+                    if self.collapsed.contains(&row.key) {
+                        "+"
+                    } else {
+                        "-"
+                    },
+                    truncate(&row.name, width.saturating_sub(indent.len() + 2)).bold()
+                ),
+            };
+
+            if index == self.selected {
+                line(&mut frame, format!("\x1b[7m{}\x1b[0m", text));
+            } else {
+                line(&mut frame, text);
+            }
+        }
+
+        line(&mut frame, "─".repeat(width));
+
+        let detail = self.detail(&rows);
+        if self.detail_scroll >= detail.len() {
+            self.detail_scroll = detail.len().saturating_sub(1);
+        }
+        for index in self.detail_scroll..self.detail_scroll + detail_height {
+            line(
+                &mut frame,
+                detail.get(index).map_or("".to_string(), |x| {
+                    // lines with colour codes are short labels so only the plain output is cut to fit
+                    if x.contains('\x1b') {
+                        x.clone()
+                    } else {
+                        truncate(x, width)
+                    }
+                }),
+            );
+        }
+
+        line(&mut frame, "─".repeat(width));
+
+        let filter = match &self.filter {
+            Some(Status::Failed) => "failed",
+            Some(Status::Passed) => "passed",
+            Some(Status::Ignored) => "ignored",
+            _ => "all",
+        };
+        let status = if self.searching {
+            format!("search: {}_", self.search)
+        } else if !self.message.is_empty() {
+            self.message.clone()
+        } else {
+            format!(
+                "{} tests | filter: {} | search: {} | j/k move  enter collapse  f filter  / search  r rerun  F rerun failed  a rerun all  J/K scroll  q quit",
+                self.groups.iter().map(|x| x.tests.len()).sum::<usize>(),
+                filter,
+                if self.search.is_empty() {
+                    "-"
+                } else {
+                    self.search.as_str()
+                }
+            )
+        };
+        frame.push_str(&truncate(&status, width));
+        frame.push_str("\x1b[K");
+
+        print!("{}", frame);
+        let _ = std::io::stdout().flush();
+    }
+
+    /// Runs the tests with the extra filters and replaces the results of the tests that were run, the other tests keep their previous results.
+    /// Every test is replaced if there are no filters.
+    fn rerun(&mut self, filters: Filters) {
+        self.message = "Running...".blue();
+        self.draw();
+
+        let args = if filters.names.is_empty() {
+            self.cmd_args.clone()
+        } else {
+            filter_args(&self.cmd_args, &filters)
+        };
+
+        let groups = match run(Some(self.cfg.clone()), Some(args)).and_then(|x| x.into_tests()) {
+            Ok(res) => res,
+            Err(RunError::BuildFailed(errors)) => {
+                self.message = format!(
+                    "{} - {}",
+                    "BUILD FAILED".red(),
+                    errors.first().map_or("", |x| x.message.as_str())
+                );
+                return;
+            }
+            Err(err) => {
+                self.message = err.with_sources().replace('\n', " ").red();
+                return;
+            }
+        };
+
+        let count: usize = groups.iter().map(|x| x.tests.len()).sum();
+        let failed = failed_tests(&groups).names.len();
+
+        if filters.names.is_empty() {
+            self.groups = groups;
+        } else {
+            merge_groups(&mut self.groups, groups);
+        }
+
+        self.message = format!("Ran {} tests, {} failed", count, failed);
+    }
+
+    /// Handles a key, returns false if the UI should close.
+    fn key(&mut self, key: Key) -> bool {
+        if self.searching {
+            match key {
+                Key::Enter | Key::Escape => self.searching = false,
+                Key::Backspace => {
+                    self.search.pop();
+                }
+                Key::Char(c) if !c.is_control() => self.search.push(c),
+                _ => {}
+            }
+            self.selected = self.first_test();
+            return true;
+        }
+
+        self.message = "".to_string();
+        if key != Key::Char('J') && key != Key::Char('K') {
+            self.detail_scroll = 0;
+        }
+
+        let rows = self.rows();
+        let page = (self.size.0 / 2).max(1);
+
+        match key {
+            Key::Char('q') | Key::Escape => return false,
+            Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => {
+                self.selected = (self.selected + 1).min(rows.len().saturating_sub(1))
+            }
+            Key::PageUp => self.selected = self.selected.saturating_sub(page),
+            Key::PageDown => {
+                self.selected = (self.selected + page).min(rows.len().saturating_sub(1))
+            }
+            Key::Char('K') => self.detail_scroll = self.detail_scroll.saturating_sub(1),
+            Key::Char('J') => self.detail_scroll += 1,
+            Key::Enter | Key::Char(' ') | Key::Left | Key::Right => {
+                if let Some(row) = rows.get(self.selected)
+                    && row.test.is_none()
+                {
+                    let collapsed = self.collapsed.contains(&row.key);
+
+                    if collapsed && key != Key::Left {
+                        self.collapsed.retain(|x| x != &row.key);
+                    } else if !collapsed && key != Key::Right {
+                        self.collapsed.push(row.key.clone());
+                    }
+                }
+            }
+            Key::Char('f') => {
+                self.filter = match self.filter {
+                    None => Some(Status::Failed),
+                    Some(Status::Failed) => Some(Status::Passed),
+                    Some(Status::Passed) => Some(Status::Ignored),
+                    _ => None,
+                };
+                self.selected = self.first_test();
+            }
+            Key::Char('/') => {
+                self.searching = true;
+                self.search = "".to_string();
+            }
+            Key::Char('r') => match self.selected_test(&rows) {
+                Some((group_index, test_index)) => {
+                    let mut filters = Filters::new();
+                    filters.add(&self.groups[group_index].tests[test_index]);
+                    self.rerun(filters);
+                }
+                None => self.message = "Select a test to rerun it".yellow(),
+            },
+            Key::Char('F') => {
+                let failed = failed_tests(&self.groups);
+
+                if failed.names.is_empty() {
+                    self.message = "There are no failed tests to rerun".yellow();
+                } else {
+                    self.rerun(failed);
+                }
+            }
+            Key::Char('a') => self.rerun(Filters::new()),
+            _ => {}
+        }
+
+        true
+    }
+}
+
+/// Runs the tests with [run] and shows the results in an interactive terminal UI until q is pressed.
+/// The tests are shown as a tree that can be collapsed, filtered by status and searched by name, the failure reason or output of the selected test is shown under the tree.
+/// The selected test, the failed tests or every test can be rerun without leaving the UI.
+/// Needs a terminal that supports ANSI escape codes and `stty`, so it only works on unix.
+/// The warnings for slow tests aren't printed as they would end up over the UI, and `--debug` output is only shown for the first run, before the UI is opened.
+pub fn tui(cfg: Config, cmd_args: Vec<String>) -> Result<(), RunError> {
+    let saved = match stty(&["-g"]) {
+        Some(res) => res,
+        None => {
+            return Err(RunError::Terminal(
+                "--tui needs to be run in a terminal that supports stty".to_string(),
+            ));
+        }
+    };
+
+    let groups = match run(Some(cfg.clone()), Some(cmd_args.clone()))? {
+        TestOutcome::Tests(res) => res,
+        res => {
+            // there is nothing to browse so the build errors are shown in the usual way
+            Display::new("Generated by ptest", res).display(cfg.display_type.clone());
+            return Ok(());
+        }
+    };
+
+    let mut state = Tui {
+        cfg: Config {
+            debug: false,
+            ..cfg
+        },
+        cmd_args,
+        groups,
+        collapsed: Vec::new(),
+        filter: None,
+        search: "".to_string(),
+        searching: false,
+        selected: 0,
+        scroll: 0,
+        detail_scroll: 0,
+        message: "".to_string(),
+        size: terminal_size(),
+    };
+    state.selected = state.first_test();
+
+    // read keys without waiting for enter or echoing them, reads return after 0.1s if no key was pressed so escape can be told apart from the arrow keys
+    // signals are still sent so ctrl+c stops a rerun, then closes the UI once the tests have stopped
+    handle_signals();
+    stty(&["-icanon", "-echo", "min", "0", "time", "1"]);
+    // the terminal is restored before the panic message is printed, otherwise it would be lost with the alternate screen
+    let default_hook = std::panic::take_hook();
+    let panic_saved = saved.clone();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal(&panic_saved);
+        default_hook(info);
+    }));
+    // switch to the alternate screen and hide the cursor
+    print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+
+    state.draw();
+    while !STOPPED.load(Ordering::SeqCst) {
+        if let Some(key) = read_key() {
+            if !state.key(key) {
+                break;
+            }
+            state.draw();
+        } else if RESIZED.load(Ordering::SeqCst) {
+            state.draw();
+        }
+    }
+
+    restore_terminal(&saved);
+
+    Ok(())
+}
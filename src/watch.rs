@@ -0,0 +1,376 @@
+use crate::build::cargo_metadata;
+use crate::config::{BacktraceStyle, Config};
+use crate::display::{Colourise, Display};
+use crate::parse::{GeneralTestType, ParsedTest, ParsedTestGroup, Status, TestOutcome};
+use crate::run::{merge_groups, run};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+/// How often the files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long the files have to stay the same after a change before the tests are rerun, so that saving several files only causes one rerun.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The files and directories that are watched in each package.
+const WATCHED: [&str; 6] = [
+    "Cargo.toml",
+    "build.rs",
+    "src",
+    "tests",
+    "benches",
+    "examples",
+];
+
+/// The options of cargo test and the test binaries that take a value, so the value isn't mistaken for a filter, see [filter_args].
+const VALUE_OPTIONS: [&str; 25] = [
+    "-p",
+    "--package",
+    "--exclude",
+    "-F",
+    "--features",
+    "--bin",
+    "--test",
+    "--example",
+    "--bench",
+    "--target",
+    "--target-dir",
+    "--manifest-path",
+    "-j",
+    "--jobs",
+    "--profile",
+    "--message-format",
+    "--color",
+    "--config",
+    "-Z",
+    "--lockfile-path",
+    "--test-threads",
+    "--skip",
+    "--format",
+    "--logfile",
+    "--shuffle-seed",
+];
+
+/// Gets the directories of every package in the workspace using `cargo metadata`, falls back to the current directory if that fails.
+fn package_dirs(cmd_args: &[String]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+
+    if let Some(metadata) = cargo_metadata(cmd_args) {
+        if let Some(root) = metadata.get("workspace_root").and_then(|x| x.as_str()) {
+            dirs.push(PathBuf::from(root));
+        }
+
+        for package in metadata
+            .get("packages")
+            .and_then(|x| x.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(dir) = package
+                .get("manifest_path")
+                .and_then(|x| x.as_str())
+                .and_then(|x| Path::new(x).parent())
+                && !dirs.contains(&dir.to_path_buf())
+            {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+    }
+
+    if dirs.is_empty() {
+        dirs.push(PathBuf::from("."));
+    }
+
+    dirs
+}
+
+/// Adds the modification time of every file under path to the snapshot, target and hidden directories are skipped.
+fn add_files(path: &Path, snapshot: &mut HashMap<PathBuf, SystemTime>) {
+    let metadata = match std::fs::metadata(path) {
+        Ok(res) => res,
+        Err(_) => return,
+    };
+
+    if metadata.is_file() {
+        if let Ok(modified) = metadata.modified() {
+            snapshot.insert(path.to_path_buf(), modified);
+        }
+        return;
+    }
+
+    let entries = match std::fs::read_dir(path) {
+        Ok(res) => res,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+
+        add_files(&entry.path(), snapshot);
+    }
+}
+
+/// Gets the modification time of every watched file in the packages.
+fn snapshot(package_dirs: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    let mut snapshot: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    for dir in package_dirs {
+        for watched in WATCHED {
+            add_files(&dir.join(watched), &mut snapshot);
+        }
+    }
+
+    snapshot
+}
+
+/// Blocks until a watched file is added, removed or modified and then stays the same for [DEBOUNCE].
+fn wait_for_change(package_dirs: &[PathBuf]) {
+    let initial = snapshot(package_dirs);
+
+    loop {
+        sleep(POLL_INTERVAL);
+
+        if snapshot(package_dirs) != initial {
+            break;
+        }
+    }
+
+    let mut previous = snapshot(package_dirs);
+
+    loop {
+        sleep(DEBOUNCE);
+
+        let current = snapshot(package_dirs);
+        if current == previous {
+            return;
+        }
+        previous = current;
+    }
+}
+
+/// The filters that select the tests to rerun, e.g. `tests::panic`.
+pub(crate) struct Filters {
+    pub(crate) names: Vec<String>,
+    /// The names are the full names of the tests so `--exact` can be passed, which stops `tests::a` from also matching `tests::ab`.
+    /// Doc-tests can't be matched exactly as rustdoc doesn't match filters against their full name, e.g. `src/lib.rs - attribute (line 3)`.
+    pub(crate) exact: bool,
+}
+
+impl Filters {
+    pub(crate) fn new() -> Filters {
+        Filters {
+            names: Vec::new(),
+            exact: true,
+        }
+    }
+
+    /// Adds the filter for a test, Doc-tests are filtered by their module path, or their file if they are at the root of it.
+    pub(crate) fn add(&mut self, test: &ParsedTest) {
+        let filter = match test.test_type {
+            GeneralTestType::Doc if test.module_path.is_empty() => {
+                test.file_path.clone().unwrap_or_default()
+            }
+            _ => test.module_path.clone(),
+        };
+
+        if test.test_type == GeneralTestType::Doc {
+            self.exact = false;
+        }
+        if !self.names.contains(&filter) {
+            self.names.push(filter);
+        }
+    }
+}
+
+/// Gets the filters that select the failed and timed out tests.
+pub(crate) fn failed_tests(groups: &[ParsedTestGroup]) -> Filters {
+    let mut filters = Filters::new();
+
+    for test in groups.iter().flat_map(|x| x.tests.iter()) {
+        if test.status == Status::Failed || test.status == Status::TimedOut {
+            filters.add(test);
+        }
+    }
+
+    filters
+}
+
+/// Replaces the filters in the args with the filters for the tests to rerun.
+/// The filters that were passed are dropped as libtest runs the tests that match any of the filters, so rerunning the failed tests of `-- parser` would run every parser test as well.
+pub(crate) fn filter_args(cmd_args: &[String], filters: &Filters) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    let mut is_value = false;
+
+    for arg in cmd_args {
+        if is_value {
+            is_value = false;
+        } else if arg == "--exact" || (arg != "--" && !arg.starts_with('-')) {
+            continue;
+        } else {
+            is_value = VALUE_OPTIONS.contains(&arg.as_str());
+        }
+
+        args.push(arg.clone());
+    }
+
+    if !args.contains(&"--".to_string()) {
+        args.push("--".to_string());
+    }
+    if filters.exact {
+        args.push("--exact".to_string());
+    }
+    args.extend(filters.names.iter().cloned());
+
+    args
+}
+
+/// Runs the tests with [run] every time a file in one of the workspace's packages changes, never returns as it runs until the process is stopped.
+/// Errors from [run] are shown and the files are still watched, so a fixed Cargo.toml is picked up.
+/// The src, tests, benches and examples directories, Cargo.toml and build.rs of each package are watched.
+/// After each run the screen is cleared and tests whose [Status] changed since the previous run are highlighted.
+/// If [Config::rerun_failed] is set then only the tests that failed in the previous run are rerun, every test is run once they all pass.
+/// The results of a rerun are merged into the previous results so that the next full run is compared against every test.
+pub fn watch(cfg: Config, cmd_args: Vec<String>) -> ! {
+    let package_dirs = package_dirs(&cmd_args);
+    let mut previous: Option<Vec<ParsedTestGroup>> = None;
+    let mut failed = Filters::new();
+
+    loop {
+        let rerun = cfg.rerun_failed && !failed.names.is_empty();
+        let args = if rerun {
+            filter_args(&cmd_args, &failed)
+        } else {
+            cmd_args.clone()
+        };
+        let message = if rerun {
+            format!(
+                "Generated by ptest - rerunning {} failed tests",
+                failed.names.len()
+            )
+        } else {
+            "Generated by ptest".to_string()
+        };
+
+        let result = run(Some(cfg.clone()), Some(args));
+
+        // clear the screen and move the cursor to the top left
+        print!("\x1b[2J\x1b[H");
+
+        match result {
+            Ok(res) => {
+                let groups = match &res {
+                    TestOutcome::Tests(groups) => Some(groups.clone()),
+                    TestOutcome::BuildFailed(_) => None,
+                };
+
+                let mut display = Display::new(message.as_str(), res);
+                display.show_output(cfg.show_output);
+                display.full_backtrace(cfg.backtrace == Some(BacktraceStyle::Full));
+                if let Some(previous) = &previous {
+                    display.previous(previous);
+                }
+                display.display(cfg.display_type.clone());
+
+                if let Some(groups) = groups {
+                    let groups = match previous.take() {
+                        Some(mut merged) if rerun => {
+                            merge_groups(&mut merged, groups);
+                            merged
+                        }
+                        _ => groups,
+                    };
+
+                    failed = failed_tests(&groups);
+                    previous = Some(groups);
+                }
+            }
+            Err(err) => println!("{}", err.with_sources().red()),
+        }
+
+        println!("\n{}", "Watching for changes, press ctrl+c to stop".blue());
+        wait_for_change(&package_dirs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{failed_tests, filter_args, Filters};
+    use crate::config::Config;
+    use crate::parse::{parse_combined, GeneralTestType, ParsedTestGroup};
+
+    fn groups(output: &str) -> Vec<ParsedTestGroup> {
+        parse_combined(output.to_string(), Config::default())
+            .unwrap()
+            .into_tests()
+            .unwrap()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn filters_failed_and_timed_out_tests() {
+        let mut groups = [
+            groups(include_str!("../tests/fixtures/failures.txt")),
+            groups(include_str!("../tests/fixtures/timeout.txt")),
+        ]
+        .concat();
+        let filters = failed_tests(&groups).names;
+
+        assert_eq!(filters.len(), 14);
+        assert_eq!(filters[0], "tests::assert_eq");
+        // Doc-tests are filtered by their module path
+        assert_eq!(filters[10..13], ["crashes", "doc_assert", "doc_err"]);
+        assert_eq!(filters[13], "tests::hangs");
+
+        // which isn't their full name so they can't be matched exactly
+        assert!(!failed_tests(&groups).exact);
+        let timed_out = failed_tests(&groups[groups.len() - 2..]);
+        assert_eq!(timed_out.names, ["tests::hangs"]);
+        assert!(timed_out.exact);
+
+        // or by their file if they are at the root of it, which is only added once
+        for test in groups[1].tests.iter_mut() {
+            assert!(matches!(test.test_type, GeneralTestType::Doc));
+            test.module_path = String::new();
+        }
+        let filters = failed_tests(&groups).names;
+        assert_eq!(filters.len(), 12);
+        assert_eq!(filters[10], "src/lib.rs");
+    }
+
+    #[test]
+    fn passes_filters_to_the_test_binaries() {
+        let mut filters = Filters::new();
+        filters.names = args(&["tests::a", "half"]);
+
+        // the full names are matched exactly so tests::a doesn't rerun tests::ab as well
+        assert_eq!(
+            filter_args(&args(&["--workspace"]), &filters),
+            args(&["--workspace", "--", "--exact", "tests::a", "half"])
+        );
+        // the filters that were passed would rerun every test they match so they are dropped, but not the values of options
+        assert_eq!(
+            filter_args(
+                &args(&["parser", "-p", "ptest", "--", "--skip", "slow", "lexer"]),
+                &filters
+            ),
+            args(&[
+                "-p", "ptest", "--", "--skip", "slow", "--exact", "tests::a", "half"
+            ])
+        );
+
+        // Doc-tests only match part of their name
+        filters.exact = false;
+        assert_eq!(
+            filter_args(&args(&["--", "--nocapture", "--exact"]), &filters),
+            args(&["--", "--nocapture", "tests::a", "half"])
+        );
+    }
+}
@@ -31,6 +31,17 @@ mod submod {
     #[test]
     fn normal_test() {}
 
+    #[test]
+    fn prints() {
+        println!("printed from a passing test");
+    }
+
+    #[test]
+    fn prints_then_fails() {
+        println!("printed before failing");
+        panic!("failed after printing")
+    }
+
     #[test]
     #[ignore = "this is the reason"]
     fn ignore() {}
@@ -103,3 +114,38 @@ mod attribute {
     /// ```
     fn edition2018() {}
 }
+
+mod names {
+    pub struct Wrapper<T, U>(pub T, pub U);
+
+    impl<T, U> Wrapper<T, U> {
+        /// ```
+        /// ```
+        pub fn first(&self) -> &T {
+            &self.0
+        }
+    }
+
+    /// ```
+    /// ```
+    fn r#type() {}
+
+    /// ```
+    /// ```
+    fn ünïcödé() {}
+
+    macro_rules! generated_test {
+        ($name:ident) => {
+            #[test]
+            fn $name() {}
+        };
+    }
+
+    generated_test!(generated_by_macro);
+
+    #[test]
+    fn r#match() {}
+
+    #[test]
+    fn ünïcödé_test() {}
+}
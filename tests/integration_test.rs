@@ -1,4 +1,13 @@
-use cargo_ptest::display::{Colour, Display};
+use cargo_ptest::bench::{compare_baseline, save_baseline};
+use cargo_ptest::config::Config;
+use cargo_ptest::display::{strip_colour, Colour, Display};
+use cargo_ptest::nextest::parse_nextest;
+use cargo_ptest::parse::{
+    attach_raw_output, decode_output, merge_streams, parse_combined, parse_list,
+    parse_list_combined, AggregateSummary, BenchResult, Crash, DocTestKind, FailureKind,
+    GeneralTestType, LineKind, ParseError, ParsedTestGroup, Status, TestOutcome,
+};
+use cargo_ptest::run::{list, report, run, RunError};
 
 #[test]
 fn colour_test_green() {
@@ -9,3 +18,1025 @@ fn colour_test_green() {
 fn colour_test_red() {
     assert_eq!(Display::colour(Colour::RED, ""), String::from("test"))
 }
+
+/// Gets the name of every test in the order they were parsed.
+fn test_names(outcome: TestOutcome) -> Vec<String> {
+    outcome
+        .into_tests()
+        .unwrap()
+        .iter()
+        .flat_map(|x| x.tests.iter())
+        .map(|x| x.name())
+        .collect()
+}
+
+#[test]
+fn parses_unusual_test_names() {
+    let output = include_str!("fixtures/names.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    assert_eq!(
+        test_names(TestOutcome::Tests(groups.clone())),
+        vec![
+            "tests::generated_by_macro",
+            "tests::r#impl::r#match",
+            "tests::r#loop",
+            "tests::r#type",
+            "tests::ünïcödé",
+            "src/lib.rs - Wrapper<T> (line 21)",
+            "src/lib.rs - Wrapper<T>::fails (line 4)",
+            "src/lib.rs - Wrapper<T>::get (line 9)",
+            "src/lib.rs - Wrapper<T>::name (line 26)",
+            "src/lib.rs - r#async::ünïcödé (line 38)",
+            "src/lib.rs - r#type (line 33)",
+        ]
+    );
+
+    // the failure reasons are found using the names in the "---- name stdout ----" lines
+    let failures: Vec<String> = groups
+        .iter()
+        .flat_map(|x| x.tests.iter())
+        .filter(|x| x.status == Status::Failed)
+        .map(|x| x.error_reason.clone().unwrap_or_default())
+        .collect();
+
+    assert_eq!(failures.len(), 2);
+    assert!(failures[0].contains("failed in a raw identifier"));
+    assert!(failures[1].contains("failed in a generic impl"));
+}
+
+#[test]
+fn parses_unusual_listed_test_names() {
+    let output = include_str!("fixtures/names_list.txt").to_string();
+    let outcome = parse_list_combined(output, String::new(), None, Config::default()).unwrap();
+
+    assert_eq!(
+        test_names(outcome),
+        vec![
+            "tests::generated_by_macro",
+            "tests::r#impl::r#match",
+            "tests::r#loop",
+            "tests::r#type",
+            "tests::ünïcödé",
+            "src/lib.rs - Wrapper<T> (line 21)",
+            "src/lib.rs - Wrapper<T>::fails (line 4)",
+            "src/lib.rs - Wrapper<T>::get (line 9)",
+            "src/lib.rs - Wrapper<T>::name (line 26)",
+            "src/lib.rs - r#async::ünïcödé (line 38)",
+            "src/lib.rs - r#type (line 33)",
+        ]
+    );
+}
+
+#[test]
+fn parses_test_names_with_spaces() {
+    // custom test harnesses can use any name, the name ends at the " ... " before the status
+    let output = "     Running tests/harness.rs (target/debug/deps/harness-0123456789abcdef)\n\nrunning 2 tests\ntest adds two numbers ... ok\ntest parses <html> & \"quotes\" ... ok\n\ntest result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s\n";
+    let outcome = parse_combined(output.to_string(), Config::default()).unwrap();
+
+    assert_eq!(
+        test_names(outcome),
+        vec!["adds two numbers", "parses <html> & \"quotes\""]
+    );
+}
+
+#[test]
+fn parses_full_ignore_reasons_and_notes() {
+    let output = include_str!("fixtures/reasons.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    let tests: Vec<_> = groups.iter().flat_map(|x| x.tests.iter()).collect();
+
+    let reason = |name: &str| {
+        tests
+            .iter()
+            .find(|x| x.name() == name)
+            .and_then(|x| x.ignore_reason.clone())
+    };
+    let note = |name: &str| {
+        tests
+            .iter()
+            .find(|x| x.name() == name)
+            .and_then(|x| x.note.clone())
+    };
+
+    assert_eq!(
+        reason("tests::db").as_deref(),
+        Some("needs DB: run with --ignored (see #42)")
+    );
+    assert_eq!(
+        reason("tests::quoted").as_deref(),
+        Some("a \"quoted\" reason\twith a tab, and a \\ backslash")
+    );
+    assert_eq!(
+        reason("more::escaped").as_deref(),
+        Some("escaped \"quotes\" and a \\ backslash")
+    );
+    assert_eq!(reason("more::dots").as_deref(), Some("wait ... for it, ok"));
+    assert_eq!(reason("more::plain"), None);
+
+    assert_eq!(note("tests::panics").as_deref(), Some("should panic"));
+    assert_eq!(
+        note("src/lib.rs - compile_fail (line 6)").as_deref(),
+        Some("compile fail")
+    );
+    assert_eq!(
+        note("src/lib.rs - no_run (line 15)").as_deref(),
+        Some("compile")
+    );
+}
+
+#[test]
+fn decodes_invalid_utf8_lossily() {
+    let (output, invalid_lines) = decode_output(include_bytes!("fixtures/invalid_utf8.txt"));
+    let mut groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    attach_raw_output(&mut groups, &invalid_lines);
+
+    let tests: Vec<_> = groups.iter().flat_map(|x| x.tests.iter()).collect();
+    assert_eq!(tests.len(), 2);
+
+    assert_eq!(
+        tests[0].output.as_deref(),
+        Some("\0\u{FFFD}\u{FFFD} binary\n")
+    );
+    assert_eq!(
+        tests[0].raw_output.as_deref(),
+        Some(&b"\0\xff\xfe binary\n"[..])
+    );
+
+    assert_eq!(tests[1].status, Status::Failed);
+    assert_eq!(tests[1].output.as_deref(), Some("caf\u{FFFD} au lait\n"));
+    assert_eq!(
+        tests[1].raw_output.as_deref(),
+        Some(&b"caf\xe9 au lait\n"[..])
+    );
+    let linear = strip_colour(&Display::new("", groups.clone()).linear());
+    assert!(linear.contains("(printed invalid UTF-8)"));
+
+    // Doc-tests are marked the same way
+    let test = &mut groups[0].tests[0];
+    test.test_type = GeneralTestType::Doc;
+    test.file_path = Some("src/lib.rs".to_string());
+    let linear = strip_colour(&Display::new("", groups).linear());
+    assert!(linear.contains(" from src/lib.rs (printed invalid UTF-8)"));
+}
+
+#[test]
+fn parses_doc_test_kinds() {
+    let output = include_str!("fixtures/reasons.txt").to_string();
+    let groups = parse_combined(output.clone(), Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    let kinds: Vec<(String, Option<DocTestKind>)> = groups
+        .iter()
+        .flat_map(|x| x.tests.iter())
+        .filter(|x| x.file_path.is_some())
+        .map(|x| (x.name(), x.doc_kind.clone()))
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (
+                "src/lib.rs - ignored (line 11)".to_string(),
+                Some(DocTestKind::Ignore)
+            ),
+            (
+                "src/lib.rs - no_run (line 15)".to_string(),
+                Some(DocTestKind::NoRun)
+            ),
+            (
+                "src/lib.rs - should_panic (line 1)".to_string(),
+                Some(DocTestKind::ShouldPanic)
+            ),
+            // merged doctests run compile_fail examples in a second batch
+            (
+                "src/lib.rs - compile_fail (line 6)".to_string(),
+                Some(DocTestKind::CompileFail)
+            ),
+        ]
+    );
+
+    let cfg = Config {
+        doc_kind: Some(DocTestKind::CompileFail),
+        ..Config::default()
+    };
+    let filtered = parse_combined(output, cfg).unwrap().into_tests().unwrap();
+
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].tests.len(), 1);
+    assert_eq!(filtered[0].tests[0].module_path, "compile_fail");
+
+    let summary = filtered[0].summary.as_ref().unwrap();
+    assert_eq!(
+        (summary.passed, summary.ignored, summary.filtered),
+        (1, 0, 3)
+    );
+}
+
+#[test]
+fn classifies_failure_kinds() {
+    let output = include_str!("fixtures/failures.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    let kinds: Vec<(String, Option<FailureKind>)> = groups
+        .iter()
+        .flat_map(|x| x.tests.iter())
+        .map(|x| (x.name(), x.failure_kind.clone()))
+        .collect();
+
+    let expected = [
+        ("tests::assert_eq", FailureKind::Assertion),
+        // assert! with a message only prints the message
+        ("tests::assert_message", FailureKind::Panic),
+        ("tests::assert_ne", FailureKind::Assertion),
+        ("tests::assert_plain", FailureKind::Assertion),
+        ("tests::did_not_panic", FailureKind::DidNotPanic),
+        ("tests::panics", FailureKind::Panic),
+        ("tests::returns_err", FailureKind::ReturnedErr),
+        ("tests::returns_io_err", FailureKind::ReturnedErr),
+        ("tests::unwrap", FailureKind::Panic),
+        ("tests::wrong_message", FailureKind::WrongPanicMessage),
+        ("src/lib.rs - crashes (line 1)", FailureKind::Crash),
+        ("src/lib.rs - doc_assert (line 11)", FailureKind::Assertion),
+        ("src/lib.rs - doc_err (line 6)", FailureKind::ReturnedErr),
+    ];
+
+    assert_eq!(
+        kinds,
+        expected
+            .iter()
+            .map(|(name, kind)| (name.to_string(), Some(kind.clone())))
+            .collect::<Vec<_>>()
+    );
+
+    let linear = strip_colour(&Display::new("", groups).linear());
+
+    assert!(linear.contains("Failures - 13 tests failed"));
+    assert!(linear.contains("├ assertion failed - 4 tests"));
+    assert!(linear.contains("Failed - tests::did_not_panic - did not panic - See reason below"));
+}
+
+#[test]
+fn parses_backtraces() {
+    let output = include_str!("fixtures/backtrace.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    let test = &groups[0].tests[0];
+
+    assert_eq!(test.backtrace.len(), 48);
+    assert_eq!(
+        test.backtrace[0].function,
+        "std[e28293b1aa0f68bd]::backtrace_rs::backtrace::libunwind::trace"
+    );
+
+    let own: Vec<(u32, &str, Option<&str>, Option<u32>)> = test
+        .backtrace
+        .iter()
+        .filter(|x| x.in_workspace)
+        .map(|x| (x.index, x.function.as_str(), x.file.as_deref(), x.line))
+        .collect();
+
+    assert_eq!(
+        own,
+        vec![
+            (
+                22,
+                "kinds::tests::unwrap",
+                Some("/tmp/kinds/src/lib.rs"),
+                Some(34)
+            ),
+            (
+                23,
+                "kinds::tests::unwrap::{{closure}}",
+                Some("/tmp/kinds/src/lib.rs"),
+                Some(34)
+            ),
+        ]
+    );
+
+    // the frames without a location are kept
+    assert_eq!(test.backtrace[5].file, None);
+
+    // the failure reason keeps the indentation of the backtrace
+    let reason = test.error_reason.as_deref().unwrap();
+    assert!(reason.contains(
+        "\n   0:     0x55ca79d5fffa - std[e28293b1aa0f68bd]::backtrace_rs::backtrace::libunwind::trace\n                               at /rustc/"
+    ));
+
+    let mut display = Display::new("", groups.clone());
+    let collapsed = strip_colour(&display.linear());
+
+    assert!(collapsed.contains("... 22 frames from std and dependencies"));
+    assert!(collapsed.contains("22: kinds::tests::unwrap\n"));
+    assert!(!collapsed.contains("libunwind::trace"));
+
+    display.full_backtrace(true);
+    assert!(strip_colour(&display.linear()).contains("0: std[e28293b1aa0f68bd]::backtrace_rs"));
+
+    // the JSON keeps the full failure reason
+    assert!(display.json().contains("libunwind::trace"));
+}
+
+#[test]
+fn keeps_results_from_crashed_binaries() {
+    let output = include_str!("fixtures/crash.txt").to_string();
+    let groups = parse_combined(output.clone(), Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    assert_eq!(groups.len(), 3);
+    assert_eq!(
+        groups[0].crashed,
+        Some(Crash {
+            status: "signal: 6, SIGABRT: process abort signal".to_string(),
+            missing: 1
+        })
+    );
+    assert_eq!(groups[1].crashed, None);
+    assert_eq!(groups[1].tests[0].module_path, "still_runs");
+
+    let statuses: Vec<(&str, &Status)> = groups[0]
+        .tests
+        .iter()
+        .map(|x| (x.module_path.as_str(), &x.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("tests::a_passes", &Status::Passed),
+            ("tests::b_fails", &Status::Failed),
+            ("tests::c_aborts", &Status::Failed),
+        ]
+    );
+    assert_eq!(groups[0].tests[2].failure_kind, Some(FailureKind::Crash));
+
+    let summary = groups[0].summary.as_ref().unwrap();
+    assert_eq!(
+        (summary.status.clone(), summary.passed, summary.failed),
+        (Status::Failed, 1, 2)
+    );
+
+    // when the tests run in parallel the test that crashed might not have printed its name
+    let output = output.replace("test tests::c_aborts ... ", "");
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    assert_eq!(groups[0].tests.len(), 2);
+    assert_eq!(groups[0].crashed.as_ref().map(|x| x.missing), Some(2));
+}
+
+#[test]
+fn reports_counts_that_do_not_match() {
+    let output = include_str!("fixtures/reasons.txt").to_string();
+    let groups = parse_combined(output.clone(), Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+
+    // a test line that went missing, e.g. it was interleaved with output from the test
+    let output = output.replace("test more::plain ... ignored\n", "");
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    let counts: Vec<(&str, u32, u32)> = groups[0]
+        .warnings
+        .iter()
+        .filter_map(|x| match x {
+            ParseError::CountMismatch {
+                count,
+                expected,
+                found,
+                ..
+            } => Some((*count, *expected, *found)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(counts, vec![("run", 7, 6), ("ignored", 5, 4)]);
+    assert!(groups[1].warnings.is_empty());
+}
+
+#[test]
+fn lists_tests_without_running_them() {
+    let output = include_str!("fixtures/workspace_list.txt").to_string();
+    let ignored_output = include_str!("fixtures/workspace_list_ignored.txt").to_string();
+    let groups = parse_list_combined(output, ignored_output, None, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    let names: Vec<(&str, usize)> = groups
+        .iter()
+        .map(|x| (x.crate_name.as_str(), x.tests.len()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("alpha", 1),
+            ("beta", 2),
+            ("gamma", 0),
+            ("alpha", 4),
+            ("beta", 4),
+            ("gamma", 1),
+        ]
+    );
+    assert!(groups.iter().all(|x| x.summary.is_none()));
+
+    let statuses: Vec<(String, &Status)> = groups[1]
+        .tests
+        .iter()
+        .map(|x| (x.name(), &x.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("tests::doubles".to_string(), &Status::Listed),
+            ("tests::slow".to_string(), &Status::Ignored),
+        ]
+    );
+
+    // Doc-tests are listed with their file and line, the ignored ones are marked from the --ignored listing
+    let doc_tests: Vec<_> = groups[3]
+        .tests
+        .iter()
+        .map(|x| {
+            (
+                x.file_path.as_deref(),
+                x.line_number,
+                &x.status,
+                x.doc_kind.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        doc_tests,
+        vec![
+            (Some("alpha/src/lib.rs"), Some(2), &Status::Listed, None),
+            (Some("alpha/src/lib.rs"), Some(8), &Status::Listed, None),
+            (
+                Some("alpha/src/lib.rs"),
+                Some(11),
+                &Status::Ignored,
+                Some(DocTestKind::Ignore)
+            ),
+            (Some("alpha/src/lib.rs"), Some(5), &Status::Listed, None),
+        ]
+    );
+}
+
+#[test]
+fn runs_and_lists_doc_tests_only() {
+    // cargo can't combine --doc with the --no-run used to build the tests first
+    let args = || {
+        ["-p", "integration", "--doc"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+    };
+
+    for outcome in [run(None, Some(args())), list(None, Some(args()))] {
+        let groups = match outcome.unwrap() {
+            TestOutcome::Tests(res) => res,
+            TestOutcome::BuildFailed(errors) => panic!("the build failed: {:?}", errors),
+        };
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0].target.as_ref().unwrap().to_string(),
+            "Doc-tests integration"
+        );
+        assert!(groups[0].tests.iter().all(|x| x.file_path.is_some()));
+    }
+}
+
+/// Gets the name, number of tests and summary counts of every group.
+fn group_counts(groups: &[ParsedTestGroup]) -> Vec<(String, usize, (u32, u32, u32))> {
+    groups
+        .iter()
+        .map(|x| {
+            let summary = x.summary.clone().unwrap();
+            (
+                x.crate_name.clone(),
+                x.tests.len(),
+                (summary.passed, summary.failed, summary.ignored),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn parses_doc_tests_of_several_crates() {
+    let output = include_str!("fixtures/workspace.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    // the Doc-tests of alpha are run in two blocks, a merged block and a block for the compile_fail and ignored tests
+    let expected = vec![
+        ("alpha".to_string(), 1, (1, 0, 0)),
+        ("beta".to_string(), 2, (1, 0, 1)),
+        ("gamma".to_string(), 0, (0, 0, 0)),
+        ("alpha".to_string(), 4, (3, 0, 1)),
+        ("beta".to_string(), 4, (3, 1, 0)),
+        ("gamma".to_string(), 1, (1, 0, 0)),
+    ];
+    assert_eq!(group_counts(&groups), expected);
+
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+    assert!(
+        groups[3..]
+            .iter()
+            .flat_map(|x| x.tests.iter())
+            .all(|x| x.file_path.is_some())
+    );
+    assert_eq!(
+        groups[4].tests[3].name(),
+        "beta/src/lib.rs - half (line 16)"
+    );
+    assert_eq!(groups[4].tests[3].status, Status::Failed);
+
+    let total = groups.aggregate_summary();
+    assert_eq!(
+        (total.status, total.passed, total.failed, total.ignored),
+        (Status::Failed, 9, 1, 2)
+    );
+
+    // the same output with stdout and stderr captured separately
+    let separate = parse_combined(
+        merge_streams(
+            include_str!("fixtures/workspace_stdout.txt"),
+            include_str!("fixtures/workspace_stderr.txt"),
+        ),
+        Config::default(),
+    )
+    .unwrap()
+    .into_tests()
+    .unwrap();
+    assert_eq!(group_counts(&separate), expected);
+
+    let listed: Vec<usize> = parse_list(
+        include_str!("fixtures/workspace_list_stdout.txt").to_string(),
+        String::new(),
+        include_str!("fixtures/workspace_list_stderr.txt").to_string(),
+        None,
+        Config::default(),
+    )
+    .unwrap()
+    .into_tests()
+    .unwrap()
+    .iter()
+    .map(|x| x.tests.len())
+    .collect();
+    assert_eq!(listed, vec![1, 2, 0, 4, 4, 1]);
+}
+
+#[test]
+fn parses_interleaved_nocapture_output() {
+    let output = include_str!("fixtures/nocapture.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+
+    // the output is printed between the test's name and its status
+    let output = |name: &str| {
+        groups[0]
+            .tests
+            .iter()
+            .find(|x| x.name() == name)
+            .and_then(|x| x.output.clone())
+    };
+    assert_eq!(
+        output("tests::a_prints_lines").as_deref(),
+        Some("first line from a\nsecond line from a\n")
+    );
+    assert_eq!(
+        output("tests::b_prints_without_newline").as_deref(),
+        Some("no newline from b\n")
+    );
+    assert_eq!(
+        output("tests::e_prints_test_like_line").as_deref(),
+        Some("test result: not really\n")
+    );
+    assert_eq!(output("tests::d_quiet"), None);
+
+    // without a failures section the output printed by a failed test is its failure reason
+    let failed = &groups[0].tests[2];
+    let reason = failed.error_reason.as_deref().unwrap();
+    assert_eq!(failed.status, Status::Failed);
+    assert!(reason.starts_with("about to fail\n") && reason.contains("c failed"));
+    assert_eq!(failed.failure_kind, Some(FailureKind::Panic));
+
+    let summary = groups[0].summary.as_ref().unwrap();
+    assert_eq!((summary.passed, summary.failed), (4, 1));
+
+    // in parallel output that was printed after a test finished can't be given to a test so it is kept in the group
+    let output = include_str!("fixtures/nocapture_parallel.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert_eq!(
+        groups[0].output.as_deref(),
+        Some("printed while other tests finish\n")
+    );
+    assert!(groups[0].tests.iter().all(|x| x.output.is_none()));
+    assert_eq!(groups[0].summary.as_ref().unwrap().passed, 3);
+}
+
+#[test]
+fn parses_successes_from_show_output() {
+    let output = include_str!("fixtures/show_output.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    let tests = &groups[0].tests;
+
+    assert_eq!(
+        tests[0].output.as_deref(),
+        Some("first line from a\nsecond line from a\n")
+    );
+    assert_eq!(tests[1].output.as_deref(), Some("no newline from b\n"));
+    assert_eq!(tests[3].output, None);
+    // a line printed by a test that looks like the summary is kept as output
+    assert_eq!(
+        tests[4].output.as_deref(),
+        Some("test result: not really\n")
+    );
+
+    // the output under "failures:" is still the failure reason
+    let reason = tests[2].error_reason.as_deref().unwrap();
+    assert_eq!(tests[2].output, None);
+    assert!(reason.contains("c failed"));
+
+    let summary = groups[0].summary.as_ref().unwrap();
+    assert_eq!((summary.passed, summary.failed), (4, 1));
+    assert!(groups[0].warnings.is_empty());
+
+    let mut display = Display::new("", groups);
+    assert!(!strip_colour(&display.linear()).contains("second line from a"));
+    display.show_output(true);
+    assert!(strip_colour(&display.linear()).contains("second line from a"));
+    assert!(strip_colour(&display.tree()).contains("second line from a"));
+    assert!(display.json().contains("second line from a"));
+}
+
+#[test]
+fn parses_benchmarks_and_compares_baselines() {
+    let output = include_str!("fixtures/bench.txt").to_string();
+    let groups = parse_combined(output.clone(), Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+
+    let benches: Vec<(String, Status, Option<BenchResult>)> = groups[0]
+        .tests
+        .iter()
+        .map(|x| (x.name(), x.status.clone(), x.bench.clone()))
+        .collect();
+    let bench = |ns_per_iter: f64, deviation: f64, mb_per_sec: Option<f64>| {
+        Some(BenchResult {
+            ns_per_iter,
+            deviation,
+            mb_per_sec,
+            baseline: None,
+        })
+    };
+    assert_eq!(
+        benches,
+        vec![
+            ("benches::a_test".to_string(), Status::Ignored, None),
+            (
+                "benches::bytes".to_string(),
+                Status::Measured,
+                bench(85.99, 40.0, Some(12047.0))
+            ),
+            (
+                "benches::slow".to_string(),
+                Status::Measured,
+                bench(87535.87, 17619.14, None)
+            ),
+            (
+                "benches::sum".to_string(),
+                Status::Measured,
+                bench(0.39, 0.14, None)
+            ),
+        ]
+    );
+    assert_eq!(groups[0].summary.as_ref().unwrap().measured, 3);
+    assert!(groups[0].warnings.is_empty());
+
+    let path = std::env::temp_dir().join("ptest-integration-test-baseline.json");
+    save_baseline(&groups, &path).unwrap();
+
+    // the same benchmarks taking twice as long
+    let slower = output.replace("0.39 ns/iter", "0.78 ns/iter");
+    let mut groups = parse_combined(slower, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    compare_baseline(&mut groups, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let sum = groups[0].tests[3].bench.as_ref().unwrap();
+    assert_eq!(sum.baseline, Some(0.39));
+    assert_eq!(sum.change(), Some(100.0));
+    let bytes = groups[0].tests[1].bench.as_ref().unwrap();
+    assert_eq!(bytes.change(), Some(0.0));
+
+    assert!(compare_baseline(&mut groups, &path).is_err());
+}
+
+#[test]
+fn parses_timed_out_tests() {
+    // the output of `cargo ptest --timeout 1 --hard-timeout 2`, the lines about the hanging test are printed by the watchdog
+    let output = include_str!("fixtures/timeout.txt").to_string();
+    let groups = parse_combined(output, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert_eq!(groups.len(), 2);
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+
+    // killing the binary after the hard timeout isn't reported as a crash
+    let test = &groups[0].tests[0];
+    assert_eq!(groups[0].crashed, None);
+    assert_eq!(test.name(), "tests::hangs");
+    assert_eq!(test.status, Status::TimedOut);
+    assert_eq!(test.slow, Some(1));
+    assert_eq!(
+        test.error_reason.as_deref(),
+        Some("timed out after 2 seconds")
+    );
+
+    assert_eq!(groups[1].tests[0].status, Status::Passed);
+
+    // a Doc-test that timed out is shown like the other tests
+    let mut groups = groups;
+    groups[1].tests[0].status = Status::TimedOut;
+    groups[1].tests[0].error_reason = Some("timed out after 2 seconds".to_string());
+    let linear = strip_colour(&Display::new("", groups).linear());
+
+    assert!(linear.contains("Timed Out - tests::hangs - timed out after 2 seconds"));
+    assert!(linear.contains("Timed Out - f from src/lib.rs - timed out after 2 seconds"));
+}
+
+#[test]
+fn parses_nextest_output() {
+    // the output of `cargo nextest run --no-fail-fast` with `retries = 1` and a slow-timeout that terminates tests after 3 seconds
+    let output = include_str!("fixtures/nextest.txt").to_string();
+    let groups = parse_nextest(output, None, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+
+    let names: Vec<&str> = groups.iter().map(|x| x.crate_name.as_str()).collect();
+    assert_eq!(names, vec!["nx", "integration"]);
+    let tests = &groups[0].tests;
+    let test = |name: &str| tests.iter().find(|x| x.name() == name).unwrap();
+
+    assert_eq!(test("tests::adds").status, Status::Passed);
+    assert_eq!(groups[1].tests[0].status, Status::Passed);
+
+    // only the last try of a retried test is kept
+    let failed = test("tests::fails");
+    let reason = failed.error_reason.as_deref().unwrap();
+    assert_eq!(failed.status, Status::Failed);
+    assert_eq!(failed.retries, 1);
+    assert!(reason.starts_with("thread 'tests::fails' (7253) panicked at src/lib.rs:18:9:\n"));
+    assert_eq!(failed.failure_kind, Some(FailureKind::Assertion));
+    assert_eq!(failed.backtrace.len(), 8);
+    assert!(failed.output.as_deref().unwrap().contains("about to fail"));
+
+    let flaky = test("tests::flaky");
+    assert_eq!((flaky.status.clone(), flaky.retries), (Status::Passed, 1));
+
+    let slow = test("tests::slow");
+    assert_eq!((slow.status.clone(), slow.slow), (Status::Passed, Some(1)));
+
+    let hangs = test("tests::hangs");
+    assert_eq!(hangs.status, Status::TimedOut);
+    assert_eq!(hangs.slow, Some(2));
+    assert_eq!(
+        hangs.error_reason.as_deref(),
+        Some("timed out after 3 seconds")
+    );
+
+    // the output of passed tests is only shown with --success-output
+    for name in ["tests::adds", "tests::flaky", "tests::slow"] {
+        assert_eq!(test(name).output, None);
+    }
+    let output = include_str!("fixtures/nextest_success_output.txt").to_string();
+    let groups = parse_nextest(output, None, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+    assert_eq!(
+        groups[1].tests[0].output.as_deref(),
+        Some("running 1 test\ntest integration_adds ... ok\n\ntest result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s\n")
+    );
+
+    // lines printed by a test that look like statuses are kept as its output
+    let output = include_str!("fixtures/nextest_logged_status.txt").to_string();
+    let groups = parse_nextest(output, None, Config::default())
+        .unwrap()
+        .into_tests()
+        .unwrap();
+    assert!(groups.iter().all(|x| x.warnings.is_empty()));
+    assert_eq!(groups.len(), 1);
+
+    let tests: Vec<(String, Status)> = groups[0]
+        .tests
+        .iter()
+        .map(|x| (x.name(), x.status.clone()))
+        .collect();
+    assert_eq!(
+        tests,
+        vec![
+            ("tests::logs".to_string(), Status::Failed),
+            ("tests::passes".to_string(), Status::Passed),
+        ]
+    );
+
+    let logs = &groups[0].tests[0];
+    let output = logs.output.as_deref().unwrap();
+    let reason = logs.error_reason.as_deref().unwrap();
+    assert!(output.contains("\nERROR [main] server started\n"));
+    assert!(reason.starts_with("FAIL [worker] connection lost\n"));
+}
+
+#[test]
+fn reports_the_output_of_a_test_binary() {
+    // the output of running a test binary directly doesn't have a Running line to name the group after
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/test_binary.txt"
+    );
+    let cfg = Config {
+        report_file: Some(path.to_string()),
+        ..Config::default()
+    };
+    let groups = report(cfg).unwrap().into_tests().unwrap();
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].crate_name, "unnamed");
+    assert!(groups[0].warnings.is_empty());
+    let statuses: Vec<Status> = groups[0].tests.iter().map(|x| x.status.clone()).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            Status::Passed,
+            Status::Failed,
+            Status::Failed,
+            Status::Ignored
+        ]
+    );
+
+    let path = std::env::temp_dir().join("ptest-integration-test-report.txt");
+    std::fs::write(
+        &path,
+        "    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.01s\n",
+    )
+    .unwrap();
+    let cfg = Config {
+        report_file: Some(path.to_string_lossy().to_string()),
+        ..Config::default()
+    };
+    let result = report(cfg);
+    let _ = std::fs::remove_file(&path);
+
+    assert!(matches!(result, Err(RunError::Parse(ParseError::NoTests))));
+}
+
+#[test]
+fn errors_chain_their_causes() {
+    use std::error::Error;
+
+    let err = ParseError::InvalidLine {
+        kind: LineKind::Summary,
+        line: "test result: maybe".to_string(),
+        line_number: Some(12),
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not parse the summary line at line 12: \"test result: maybe\""
+    );
+    let err = ParseError::InvalidLine {
+        kind: LineKind::Running,
+        line: "Running".to_string(),
+        line_number: None,
+    };
+    assert_eq!(
+        err.to_string(),
+        "Could not parse the Running line: \"Running\""
+    );
+    let err = ParseError::CountMismatch {
+        group: "alpha".to_string(),
+        count: "passed",
+        expected: 2,
+        found: 1,
+    };
+    assert_eq!(
+        err.to_string(),
+        "The counts for alpha don't match, the output says 2 passed but 1 were parsed"
+    );
+    assert!(err.source().is_none());
+
+    // the cause is only given by source so it isn't repeated when the chain is printed, e.g. by anyhow
+    let err = RunError::from(ParseError::MissingSummary {
+        group: "alpha".to_string(),
+    });
+    assert_eq!(err.to_string(), "the output of cargo could not be parsed");
+    assert_eq!(
+        err.source().map(|x| x.to_string()).as_deref(),
+        Some("Could not find the summary line for alpha")
+    );
+    assert_eq!(
+        err.with_sources(),
+        "the output of cargo could not be parsed: Could not find the summary line for alpha"
+    );
+
+    let err = RunError::Spawn {
+        command: "nextest".to_string(),
+        source: std::io::Error::new(std::io::ErrorKind::NotFound, "not found"),
+    };
+    assert_eq!(err.to_string(), "'cargo nextest' could not be started");
+    assert_eq!(
+        err.with_sources(),
+        "'cargo nextest' could not be started: not found"
+    );
+
+    let err = RunError::Io {
+        context: "could not read stdin".to_string(),
+        source: std::io::Error::new(std::io::ErrorKind::BrokenPipe, "broken pipe"),
+    };
+    assert!(err.source().is_some_and(|x| x.is::<std::io::Error>()));
+    assert_eq!(err.with_sources(), "could not read stdin: broken pipe");
+
+    let err = RunError::Config("Invalid argument --bogus".to_string());
+    assert!(err.source().is_none());
+    assert_eq!(err.with_sources(), "Invalid argument --bogus");
+    assert!(matches!(
+        TestOutcome::BuildFailed(Vec::new()).into_tests(),
+        Err(RunError::BuildFailed(_))
+    ));
+}
+
+#[test]
+fn parses_leniently() {
+    // the custom harness of tests/custom.rs prints a summary without the time, as libtest did before 1.52
+    let output = include_str!("fixtures/lenient.txt").to_string();
+    let err = parse_combined(output.clone(), Config::default()).err();
+    let expected = ParseError::InvalidLine {
+        kind: LineKind::Summary,
+        line: "test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out"
+            .to_string(),
+        line_number: Some(15),
+    };
+    assert_eq!(err, Some(expected.clone()));
+
+    let cfg = Config {
+        lenient: true,
+        ..Config::default()
+    };
+    let groups = parse_combined(output, cfg).unwrap().into_tests().unwrap();
+    let names: Vec<&str> = groups.iter().map(|x| x.crate_name.as_str()).collect();
+    assert_eq!(names, vec!["lenient", "custom", "lenient"]);
+
+    // the tests before the line that couldn't be parsed are kept
+    let custom = &groups[1];
+    assert_eq!(custom.tests.len(), 2);
+    assert!(custom.tests.iter().all(|x| x.status == Status::Passed));
+    assert_eq!(custom.warnings[0], expected);
+    assert!(matches!(
+        custom.warnings[1],
+        ParseError::MissingSummary { .. }
+    ));
+    assert!(groups[0].warnings.is_empty() && groups[2].warnings.is_empty());
+}